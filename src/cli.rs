@@ -1,8 +1,55 @@
-use clap::Parser;
+use std::path::PathBuf;
+
+use clap::{Parser, ValueEnum};
+
+use crate::{
+    grid::{self, Columns},
+    keys::{self, KeyBinding},
+    layout::EllipsisMode,
+    marks::MultiOrder,
+    mode::ModeKind,
+    position::{self, Monitor, Position, Size},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Layout {
+    /// Input at the bottom, best match closest to it
+    Default,
+    /// Input at the top, matches below it
+    Reverse,
+    /// Input at the bottom, matches listed downward from the top
+    ReverseList,
+    /// Prompt, input and matches on a single line, like dmenu
+    Horizontal,
+}
 
 #[derive(Parser)]
 #[command(version)]
 pub struct Cli {
+    /// Modes to show, switch between them with ctrl-tab/ctrl-shift-tab (default: stdin, or script with --script)
+    #[arg(long, value_delimiter = ',')]
+    pub mode: Vec<ModeKind>,
+    /// Rofi-compatible script used by the script mode
+    #[arg(long)]
+    pub script: Option<PathBuf>,
+    /// Open the host picked in the ssh mode with `$TERMINAL -e ssh` instead of printing it
+    #[arg(long)]
+    pub ssh_connect: bool,
+    /// Directory to start the files mode in
+    #[arg(default_value = ".")]
+    pub dir: PathBuf,
+    /// Show hidden files in the files mode, toggle with ctrl-h
+    #[arg(long)]
+    pub hidden: bool,
+    /// Max depth of the files listed in the files mode, 1 lists only the current directory
+    #[arg(long, default_value_t = 1)]
+    pub max_depth: usize,
+    /// Print the codepoint (U+XXXX) of the character picked in the emoji mode
+    #[arg(long)]
+    pub codepoint: bool,
+    /// Accept dmenu's flags like `-l 10 -p Run: -fn Mono-12`, also enabled when invoked as `dmenu`
+    #[arg(long)]
+    pub dmenu_compat: bool,
     /// Exit immediately when there's no match
     #[arg(long = "exit-0", short = '0')]
     pub exit_if_empty: bool,
@@ -24,6 +71,42 @@ pub struct Cli {
     /// Enable multi-select with tab/shift-tab (takes optional limit to the number of matches)
     #[arg(long, short, num_args = 0..=1, default_missing_value = "999999999")]
     pub multi: Option<usize>,
+    /// Print the marked items in input, selection or score order
+    #[arg(long, value_enum, default_value_t = MultiOrder::Selection)]
+    pub multi_order: MultiOrder,
+    /// Move the pointer to the match under the mouse
+    #[arg(long)]
+    pub hover_select: bool,
+    /// Mask the input and print it on enter, without listing any items
+    #[arg(long, short = 'P')]
+    pub password: bool,
+    /// Save the accepted queries to FILE, go through them with ctrl-up/ctrl-down
+    #[arg(long, value_name = "FILE")]
+    pub history: Option<PathBuf>,
+    /// Rank the items picked often and recently higher, keeping the counts in FILE
+    #[arg(long, value_name = "FILE")]
+    pub frecency_db: Option<PathBuf>,
+    /// Max number of queries kept in the history
+    #[arg(long, default_value_t = 1000)]
+    pub history_size: usize,
+    /// Bind keys to actions, comma separated `KEY:ACTION` pairs like `alt-p:history-prev`
+    #[arg(long, value_delimiter = ',', value_parser = keys::parse_binding)]
+    pub bind: Vec<KeyBinding>,
+    /// How the input and the matches are laid out
+    #[arg(long, value_enum, default_value_t = Layout::Reverse)]
+    pub layout: Layout,
+    /// Arrange the matches in a grid of N columns, or as many as fit with `auto`
+    #[arg(long, value_name = "N|auto", value_parser = grid::parse_columns)]
+    pub columns: Option<Columns>,
+    /// Show the icons of the items, from icon themes or image paths
+    #[arg(long)]
+    pub show_icons: bool,
+    /// Icon theme to look the icons up in, falling back to hicolor
+    #[arg(long, default_value_t = String::from("hicolor"))]
+    pub icon_theme: String,
+    /// Show a thumbnail of the highlighted item right of the list when it's an image file
+    #[arg(long)]
+    pub image_preview: bool,
     /// Input prompt
     #[arg(long, default_value_t = String::from(""))]
     pub prompt: String,
@@ -39,19 +122,65 @@ pub struct Cli {
     // /// Ellipsis to show when line is truncated
     // #[arg(long, default_value_t = '…')]
     // pub ellipsis: char,
+    /// Where long matches are cut: start, middle, end, or smart to collapse the middle
+    /// directories of paths
+    #[arg(long, value_enum, default_value_t = EllipsisMode::End)]
+    pub ellipsis_mode: EllipsisMode,
+    /// Wrap long matches over several lines instead of cutting them
+    #[arg(long)]
+    pub wrap: bool,
     /// Set border color, name or hex value
     #[arg(long, default_value_t = String::from("gray"))]
     pub border_color: String,
+    /// Set background color, name or hex value
+    #[arg(long)]
+    pub background_color: Option<String>,
+    /// Set text color, name or hex value
+    #[arg(long, default_value_t = String::from("gray"))]
+    pub text_color: String,
+    /// Set background color of the selected row, name or hex value
+    #[arg(long)]
+    pub selected_background_color: Option<String>,
+    /// Set text color of the selected row, name or hex value
+    #[arg(long)]
+    pub selected_text_color: Option<String>,
     /// Set font size
     #[arg(long, default_value_t = 16.0)]
     pub font_size: f32,
-    /// Set font family (must be monospaced)
-    #[arg(long)]
-    pub font: Option<String>,
-    /// Set window height
-    #[arg(long, default_value_t = 510.0)]
-    pub window_height: f32,
-    /// Set window width
-    #[arg(long, default_value_t = 480.0)]
-    pub window_width: f32,
+    /// Set font family, or comma separated fallbacks like `Inter,Noto Sans CJK JP`
+    #[arg(long, value_delimiter = ',', value_parser = parse_font)]
+    pub font: Vec<String>,
+    /// Set window height, in points or percent of the monitor like `50%`
+    #[arg(long, alias = "height", default_value = "510", value_parser = position::parse_size)]
+    pub window_height: Size,
+    /// Size the window to show N rows, instead of --window-height
+    #[arg(long, short, value_name = "N")]
+    pub lines: Option<u32>,
+    /// Shrink the window to the number of matches, up to --lines or --window-height
+    #[arg(long)]
+    pub auto_height: bool,
+    /// Set window width, in points or percent of the monitor like `40%`
+    #[arg(long, alias = "width", default_value = "480", value_parser = position::parse_size)]
+    pub window_width: Size,
+    /// Place the window at center, top, bottom, left, right, top-left, top-right, bottom-left,
+    /// bottom-right, pointer or x,y of the monitor
+    #[arg(long, default_value = "center", value_parser = position::parse_position)]
+    pub position: Position,
+    /// Move the window horizontally from its position
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    pub x_offset: f32,
+    /// Move the window vertically from its position
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    pub y_offset: f32,
+    /// Monitor to show the window on: its index, focused or pointer (default: primary)
+    #[arg(long, value_parser = position::parse_monitor)]
+    pub monitor: Option<Monitor>,
+}
+
+/// Font family name, without the spaces around it in a list
+fn parse_font(font: &str) -> anyhow::Result<String> {
+    match font.trim() {
+        "" => anyhow::bail!("The font name is empty"),
+        font => Ok(font.to_string()),
+    }
 }
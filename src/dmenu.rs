@@ -0,0 +1,169 @@
+use std::path::Path;
+
+/// Points to pixels at the 96 dpi dmenu assumes
+fn pt_to_px(pt: f32) -> f32 {
+    pt * 96.0 / 72.0
+}
+
+/// Whether the dmenu flags should be translated, when invoked as `dmenu` or with `--dmenu-compat`
+pub fn is_compat(args: &[String]) -> bool {
+    let invoked_as_dmenu = args
+        .first()
+        .and_then(|arg0| Path::new(arg0).file_stem())
+        .is_some_and(|name| name == "dmenu");
+
+    invoked_as_dmenu || args.iter().any(|arg| arg == "--dmenu-compat")
+}
+
+/// Replace dmenu's single-dash flags with the matching emenu flags
+pub fn translate_args(args: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut args = args.into_iter();
+    let mut translated = Vec::new();
+    let mut vertical = false;
+
+    while let Some(arg) = args.next() {
+        let flag = match arg.as_str() {
+            "-l" => {
+                vertical = true;
+                "--lines"
+            }
+            "-p" => "--prompt",
+            "-nb" => "--background-color",
+            "-nf" => "--text-color",
+            "-sb" => "--selected-background-color",
+            "-sf" => "--selected-text-color",
+            "-v" => "--version",
+            "-m" => "--monitor",
+            "-fn" => {
+                if let Some(font) = args.next() {
+                    translated.extend(translate_font(&font));
+                }
+                continue;
+            }
+            // Grabbing the keyboard first is what emenu does anyway
+            "-f" => continue,
+            "-b" => {
+                translated.extend(["--position".to_string(), "bottom".to_string()]);
+                continue;
+            }
+            // Embedding into another window isn't supported, skip its id
+            "-w" => {
+                args.next();
+                continue;
+            }
+            _ => {
+                translated.push(arg);
+                continue;
+            }
+        };
+
+        translated.push(flag.to_string());
+    }
+
+    // Without -l dmenu is a single line
+    if !vertical && !translated.iter().any(|arg| arg.starts_with("--layout")) {
+        translated.extend(["--layout".to_string(), "horizontal".to_string()]);
+    }
+
+    translated
+}
+
+/// Font and size from an xft font like `Mono-12` or `Mono:size=12`
+fn translate_font(font: &str) -> Vec<String> {
+    let mut options = font.split(':');
+    let name = options.next().unwrap_or_default();
+
+    let (mut family, mut size) = match name.rsplit_once('-') {
+        Some((family, size)) => match size.parse::<f32>() {
+            Ok(size) => (family, Some(pt_to_px(size))),
+            Err(_) => (name, None),
+        },
+        None => (name, None),
+    };
+
+    for option in options {
+        match option.split_once('=') {
+            Some(("size", pt)) => size = pt.parse::<f32>().ok().map(pt_to_px).or(size),
+            Some(("pixelsize", px)) => size = px.parse().ok().or(size),
+            _ => {}
+        }
+    }
+
+    family = family.trim();
+
+    let mut args = Vec::new();
+    if !family.is_empty() {
+        args.extend(["--font".to_string(), family.to_string()]);
+    }
+    if let Some(size) = size {
+        args.extend(["--font-size".to_string(), size.to_string()]);
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[rstest]
+    #[case(&["dmenu", "-l", "10"], true)]
+    #[case(&["/usr/local/bin/dmenu"], true)]
+    #[case(&["emenu", "--dmenu-compat"], true)]
+    #[case(&["emenu", "-l"], false)]
+    #[case(&["dmenu_run"], false)]
+    fn test_is_compat(#[case] argv: &[&str], #[case] expected: bool) {
+        assert_eq!(is_compat(&args(argv)), expected);
+    }
+
+    #[rstest]
+    #[case(
+        &["dmenu", "-i", "-l", "10", "-p", "Run:"],
+        &["dmenu", "-i", "--lines", "10", "--prompt", "Run:"]
+    )]
+    #[case(
+        &["dmenu", "-nb", "#222", "-nf", "#bbbbbb", "-sb", "#005577", "-sf", "#eeeeee"],
+        &[
+            "dmenu",
+            "--background-color", "#222",
+            "--text-color", "#bbbbbb",
+            "--selected-background-color", "#005577",
+            "--selected-text-color", "#eeeeee",
+            "--layout", "horizontal",
+        ]
+    )]
+    #[case(
+        &["dmenu", "-fn", "Mono-12"],
+        &["dmenu", "--font", "Mono", "--font-size", "16", "--layout", "horizontal"]
+    )]
+    #[case(
+        &["dmenu", "-b", "-f", "-m", "1", "-w", "0x1", "-P"],
+        &["dmenu", "--position", "bottom", "--monitor", "1", "-P", "--layout", "horizontal"]
+    )]
+    #[case(
+        &["emenu", "--dmenu-compat", "--cycle"],
+        &["emenu", "--dmenu-compat", "--cycle", "--layout", "horizontal"]
+    )]
+    #[case(
+        &["emenu", "--dmenu-compat", "--layout", "reverse"],
+        &["emenu", "--dmenu-compat", "--layout", "reverse"]
+    )]
+    fn test_translate_args(#[case] argv: &[&str], #[case] expected: &[&str]) {
+        assert_eq!(translate_args(args(argv)), args(expected));
+    }
+
+    #[rstest]
+    #[case("Mono-12", &["--font", "Mono", "--font-size", "16"])]
+    #[case("DejaVu Sans Mono-9", &["--font", "DejaVu Sans Mono", "--font-size", "12"])]
+    #[case("monospace:size=12:antialias=true", &["--font", "monospace", "--font-size", "16"])]
+    #[case("Hack:pixelsize=14", &["--font", "Hack", "--font-size", "14"])]
+    #[case("Fira-Code", &["--font", "Fira-Code"])]
+    #[case(":size=9", &["--font-size", "12"])]
+    fn test_translate_font(#[case] font: &str, #[case] expected: &[&str]) {
+        assert_eq!(translate_font(font), args(expected));
+    }
+}
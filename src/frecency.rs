@@ -0,0 +1,181 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Max number of items kept in the database, the lowest ranked are dropped
+const MAX_ENTRIES: usize = 1000;
+
+/// Scales the frecency to be comparable with nucleo's scores
+const BOOST_SCALE: f64 = 32.0;
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Entry {
+    count: u32,
+    /// Seconds since the unix epoch
+    last_used: u64,
+}
+
+impl Entry {
+    /// Use count weighted by how recently it was used
+    fn frecency(&self, now: u64) -> f64 {
+        let weight = match now.saturating_sub(self.last_used) {
+            age if age < HOUR => 4.0,
+            age if age < DAY => 2.0,
+            age if age < WEEK => 0.5,
+            _ => 0.25,
+        };
+
+        self.count as f64 * weight
+    }
+}
+
+/// How often and how recently each item was picked, keyed by its text
+pub struct Frecency {
+    path: PathBuf,
+    entries: HashMap<String, Entry>,
+    now: u64,
+}
+
+impl Frecency {
+    /// Load the database, a missing file is an empty database
+    pub fn load(path: PathBuf) -> Self {
+        let entries = fs::read_to_string(&path)
+            .map(|contents| parse_entries(&contents))
+            .unwrap_or_default();
+
+        Self {
+            path,
+            entries,
+            now: now(),
+        }
+    }
+
+    /// Score added to the match score of the item
+    pub fn boost(&self, text: &str) -> u32 {
+        self.entries.get(text).map_or(0, |entry| {
+            (entry.frecency(self.now).ln_1p() * BOOST_SCALE) as u32
+        })
+    }
+
+    /// Count a use of the item
+    pub fn record(&mut self, text: &str) {
+        if text.contains('\n') {
+            return;
+        }
+
+        let entry = self.entries.entry(text.to_string()).or_insert(Entry {
+            count: 0,
+            last_used: self.now,
+        });
+        entry.count += 1;
+        entry.last_used = self.now;
+    }
+
+    /// Write the database, keeping the highest ranked entries
+    pub fn save(&self) -> io::Result<()> {
+        let mut entries = self.entries.iter().collect::<Vec<_>>();
+        entries.sort_by(|(_, a), (_, b)| b.frecency(self.now).total_cmp(&a.frecency(self.now)));
+        entries.truncate(MAX_ENTRIES);
+
+        let contents = entries
+            .into_iter()
+            .map(|(text, entry)| format!("{}\t{}\t{text}\n", entry.count, entry.last_used))
+            .collect::<String>();
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, contents)
+    }
+}
+
+/// Lines of `COUNT\tLAST_USED\tTEXT`, invalid lines are skipped
+fn parse_entries(contents: &str) -> HashMap<String, Entry> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let count = fields.next()?.parse().ok()?;
+            let last_used = fields.next()?.parse().ok()?;
+            let text = fields.next()?;
+
+            Some((text.to_string(), Entry { count, last_used }))
+        })
+        .collect()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn test_parse_entries() {
+        let entries = parse_entries("3\t100\tfirefox\ninvalid\n1\t200\tmy\ttabbed item\n");
+
+        assert_eq!(
+            entries,
+            HashMap::from([
+                (
+                    "firefox".to_string(),
+                    Entry {
+                        count: 3,
+                        last_used: 100
+                    }
+                ),
+                (
+                    "my\ttabbed item".to_string(),
+                    Entry {
+                        count: 1,
+                        last_used: 200
+                    }
+                ),
+            ])
+        );
+    }
+
+    #[rstest]
+    #[case(1, 0, 4.0)]
+    #[case(3, 2 * HOUR, 6.0)]
+    #[case(2, 3 * DAY, 1.0)]
+    #[case(4, 2 * WEEK, 1.0)]
+    fn test_frecency(#[case] count: u32, #[case] age: u64, #[case] expected: f64) {
+        let now = 10 * WEEK;
+        let entry = Entry {
+            count,
+            last_used: now - age,
+        };
+
+        assert_eq!(entry.frecency(now), expected);
+    }
+
+    #[test]
+    fn test_boost() {
+        let mut frecency = Frecency {
+            path: PathBuf::new(),
+            entries: HashMap::new(),
+            now: 10 * WEEK,
+        };
+
+        frecency.record("often");
+        frecency.record("often");
+        frecency.record("once");
+
+        assert_eq!(frecency.boost("never"), 0);
+        assert!(frecency.boost("often") > frecency.boost("once"));
+        assert!(frecency.boost("once") > 0);
+    }
+}
@@ -0,0 +1,140 @@
+/// Number of columns of the match grid
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Columns {
+    Fixed(u32),
+    /// As many as fit the widest match
+    Auto,
+}
+
+/// Parse a number of columns or `auto`
+pub fn parse_columns(columns: &str) -> anyhow::Result<Columns> {
+    match columns {
+        "auto" => Ok(Columns::Auto),
+        n => match n.parse()? {
+            0 => anyhow::bail!("There must be at least one column"),
+            n => Ok(Columns::Fixed(n)),
+        },
+    }
+}
+
+/// The visible part of the grid and the selected cell in it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GridView {
+    pub columns: u32,
+    /// Rows that fit in the list
+    pub rows: u32,
+    /// Rows kept visible around the selection when scrolling
+    pub scroll_offset: u32,
+    pub cycle: bool,
+}
+
+impl GridView {
+    /// Move the selection by `delta` matches, scrolling by whole rows.
+    /// Returns the new `first_idx` and `selected_idx`.
+    pub fn step(&self, first_idx: u32, selected_idx: u32, delta: i64, matched: u32) -> (u32, u32) {
+        if matched == 0 || self.rows == 0 {
+            return (0, 0);
+        }
+
+        let current = (first_idx + selected_idx) as i64;
+        let target = current + delta;
+        let target = if (0..matched as i64).contains(&target) {
+            target
+        } else if self.cycle {
+            target.rem_euclid(matched as i64)
+        } else if delta.abs() < self.columns as i64 {
+            // Left and right stop at the ends
+            target.clamp(0, matched as i64 - 1)
+        } else {
+            // Up and down stay in the column when there's no row there
+            current
+        };
+        let target = target as u32;
+
+        let row = target / self.columns;
+        let last_row = (matched - 1) / self.columns;
+        let offset = self.scroll_offset.min(self.rows.saturating_sub(1) / 2);
+
+        let mut first_row = first_idx / self.columns;
+        if row < first_row + offset {
+            first_row = row.saturating_sub(offset);
+        } else if row + offset >= first_row + self.rows {
+            first_row = row + offset + 1 - self.rows;
+        }
+        first_row = first_row.min((last_row + 1).saturating_sub(self.rows));
+
+        let first_idx = first_row * self.columns;
+        (first_idx, target - first_idx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("auto", Columns::Auto)]
+    #[case("4", Columns::Fixed(4))]
+    fn test_parse_columns(#[case] columns: &str, #[case] expected: Columns) {
+        assert_eq!(parse_columns(columns).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case("0")]
+    #[case("-1")]
+    #[case("many")]
+    fn test_parse_columns_error(#[case] columns: &str) {
+        assert!(parse_columns(columns).is_err());
+    }
+
+    // 4 columns and 3 visible rows of 22 matches, so 6 rows in total
+    #[rstest]
+    #[case::right((0, 0), 1, (0, 1))]
+    #[case::down((0, 1), 4, (0, 5))]
+    #[case::left_at_start((0, 0), -1, (0, 0))]
+    #[case::up_at_start((0, 2), -4, (0, 2))]
+    #[case::scroll_down((0, 9), 4, (4, 9))]
+    #[case::scroll_up((8, 1), -4, (4, 1))]
+    #[case::right_wraps_row((0, 3), 1, (0, 4))]
+    #[case::down_to_last_row((12, 5), 4, (12, 9))]
+    #[case::down_without_cell((12, 7), 4, (12, 7))]
+    #[case::right_at_end((12, 9), 1, (12, 9))]
+    fn test_step(#[case] from: (u32, u32), #[case] delta: i64, #[case] expected: (u32, u32)) {
+        let view = GridView {
+            columns: 4,
+            rows: 3,
+            scroll_offset: 0,
+            cycle: false,
+        };
+
+        assert_eq!(view.step(from.0, from.1, delta, 22), expected);
+    }
+
+    #[rstest]
+    #[case((0, 0), -1, (12, 9))]
+    #[case((12, 9), 1, (0, 0))]
+    fn test_step_cycle(#[case] from: (u32, u32), #[case] delta: i64, #[case] expected: (u32, u32)) {
+        let view = GridView {
+            columns: 4,
+            rows: 3,
+            scroll_offset: 0,
+            cycle: true,
+        };
+
+        assert_eq!(view.step(from.0, from.1, delta, 22), expected);
+    }
+
+    #[test]
+    fn test_step_scroll_offset() {
+        let view = GridView {
+            columns: 2,
+            rows: 5,
+            scroll_offset: 1,
+            cycle: false,
+        };
+
+        // Scrolls when the selection gets to the second to last row
+        assert_eq!(view.step(0, 6, 2, 20), (2, 6));
+    }
+}
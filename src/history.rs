@@ -0,0 +1,121 @@
+use std::{fs, io, path::PathBuf};
+
+/// Accepted queries, oldest first
+pub struct History {
+    path: PathBuf,
+    entries: Vec<String>,
+    max_size: usize,
+    /// Entry shown in the input, `entries.len()` when not navigating
+    pos: usize,
+    /// What was typed before starting to navigate
+    draft: String,
+}
+
+impl History {
+    /// Load the history, a missing file is an empty history
+    pub fn load(path: PathBuf, max_size: usize) -> Self {
+        let entries = fs::read_to_string(&path)
+            .map(|contents| contents.lines().map(String::from).collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        Self::new(path, entries, max_size)
+    }
+
+    fn new(path: PathBuf, entries: Vec<String>, max_size: usize) -> Self {
+        Self {
+            path,
+            pos: entries.len(),
+            entries,
+            max_size,
+            draft: String::new(),
+        }
+    }
+
+    /// Step to an older query, `input` is kept to come back to it
+    pub fn prev(&mut self, input: &str) -> Option<&str> {
+        if self.pos == 0 {
+            return None;
+        }
+
+        if self.pos == self.entries.len() {
+            self.draft = input.to_string();
+        }
+
+        self.pos -= 1;
+        Some(&self.entries[self.pos])
+    }
+
+    /// Step to a newer query, or back to what was typed
+    pub fn next(&mut self) -> Option<&str> {
+        if self.pos >= self.entries.len() {
+            return None;
+        }
+
+        self.pos += 1;
+        Some(self.entries.get(self.pos).unwrap_or(&self.draft))
+    }
+
+    /// Add the query as the newest entry and write the history
+    pub fn save(&mut self, query: &str) -> io::Result<()> {
+        self.push(query);
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, self.entries.join("\n") + "\n")
+    }
+
+    fn push(&mut self, query: &str) {
+        if query.trim().is_empty() || query.contains('\n') {
+            return;
+        }
+
+        self.entries.retain(|e| e != query);
+        self.entries.push(query.to_string());
+
+        let overflow = self.entries.len().saturating_sub(self.max_size);
+        self.entries.drain(..overflow);
+        self.pos = self.entries.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(entries: &[&str], max_size: usize) -> History {
+        History::new(
+            PathBuf::new(),
+            entries.iter().map(|e| e.to_string()).collect(),
+            max_size,
+        )
+    }
+
+    #[test]
+    fn test_navigation() {
+        let mut history = history(&["one", "two"], 10);
+
+        assert_eq!(history.next(), None);
+        assert_eq!(history.prev("typed"), Some("two"));
+        assert_eq!(history.prev("two"), Some("one"));
+        assert_eq!(history.prev("one"), None);
+        assert_eq!(history.next(), Some("two"));
+        assert_eq!(history.next(), Some("typed"));
+        assert_eq!(history.next(), None);
+    }
+
+    #[test]
+    fn test_push() {
+        let mut history = history(&["one", "two", "three"], 3);
+
+        history.push("one");
+        assert_eq!(history.entries, ["two", "three", "one"]);
+
+        history.push("four");
+        assert_eq!(history.entries, ["three", "one", "four"]);
+
+        history.push("  ");
+        assert_eq!(history.entries, ["three", "one", "four"]);
+        assert_eq!(history.prev(""), Some("four"));
+    }
+}
@@ -0,0 +1,352 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env, fs,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+use eframe::egui::{ColorImage, Context, TextureHandle, TextureOptions};
+use image::{ImageReader, imageops::FilterType};
+use resvg::{tiny_skia, usvg};
+
+use crate::mode::data_home;
+
+/// Theme searched after the ones asked for, it has the icons of most applications
+const FALLBACK_THEME: &str = "hicolor";
+
+/// Formats that can be decoded, in order of preference
+const EXTENSIONS: [&str; 3] = ["png", "svg", "jpg"];
+
+/// Directories and inherited themes listed in an `index.theme`
+#[derive(Debug, Default, PartialEq)]
+struct ThemeIndex {
+    inherits: Vec<String>,
+    /// Subdirectories with the size of their icons
+    dirs: Vec<(String, u32)>,
+}
+
+/// An icon theme, its folders can be split across the data dirs
+struct Theme {
+    roots: Vec<PathBuf>,
+    index: ThemeIndex,
+}
+
+/// Icon textures, looked up and decoded in another thread
+pub struct Icons {
+    ctx: Context,
+    /// `None` while the icon is loading or when it wasn't found
+    textures: HashMap<String, Option<TextureHandle>>,
+    requests: Sender<String>,
+    decoded: Receiver<(String, Option<ColorImage>)>,
+}
+
+impl Icons {
+    /// Look up the icons in `theme` and its parents, scaled down to `size` pixels
+    pub fn new(ctx: Context, theme: String, size: u32) -> Self {
+        let (requests, pending) = mpsc::channel::<String>();
+        let (done, decoded) = mpsc::channel();
+        let repaint = ctx.clone();
+
+        thread::spawn(move || {
+            let base_dirs = base_dirs();
+            let themes = load_themes(&theme, &base_dirs);
+
+            for name in pending {
+                let image = find_icon(&name, size, &themes, &base_dirs)
+                    .and_then(|path| decode(&path, size));
+                if done.send((name, image)).is_err() {
+                    break;
+                }
+                repaint.request_repaint();
+            }
+        });
+
+        Self {
+            ctx,
+            textures: HashMap::new(),
+            requests,
+            decoded,
+        }
+    }
+
+    /// Texture of an icon name or path, loading it the first time it's asked for
+    pub fn get(&mut self, name: &str) -> Option<&TextureHandle> {
+        while let Ok((name, image)) = self.decoded.try_recv() {
+            let texture =
+                image.map(|image| self.ctx.load_texture(&name, image, TextureOptions::LINEAR));
+            self.textures.insert(name, texture);
+        }
+
+        self.textures
+            .entry(name.to_string())
+            .or_insert_with(|| {
+                let _ = self.requests.send(name.to_string());
+                None
+            })
+            .as_ref()
+    }
+}
+
+/// `icons` folders of the XDG data dirs and `~/.icons`, by priority
+fn base_dirs() -> Vec<PathBuf> {
+    let data_dirs =
+        env::var("XDG_DATA_DIRS").unwrap_or_else(|_| "/usr/local/share:/usr/share".to_string());
+
+    env::home_dir()
+        .map(|home| home.join(".icons"))
+        .into_iter()
+        .chain(data_home().map(|dir| dir.join("icons")))
+        .chain(env::split_paths(&data_dirs).map(|dir| dir.join("icons")))
+        .collect()
+}
+
+/// The theme and the ones it inherits from depth first, then the fallback theme
+fn load_themes(name: &str, base_dirs: &[PathBuf]) -> Vec<Theme> {
+    let mut themes = Vec::new();
+    let mut seen = HashSet::new();
+    let mut stack = vec![FALLBACK_THEME.to_string(), name.to_string()];
+
+    while let Some(name) = stack.pop() {
+        if !seen.insert(name.clone()) {
+            continue;
+        }
+
+        let roots = base_dirs
+            .iter()
+            .map(|dir| dir.join(&name))
+            .filter(|dir| dir.is_dir())
+            .collect::<Vec<_>>();
+        let Some(index) = roots
+            .iter()
+            .find_map(|root| fs::read_to_string(root.join("index.theme")).ok())
+        else {
+            continue;
+        };
+
+        let index = parse_index(&index);
+        stack.extend(index.inherits.iter().rev().cloned());
+        themes.push(Theme { roots, index });
+    }
+
+    themes
+}
+
+/// Parse the `Directories`, `Inherits` and directory sizes of an `index.theme`
+fn parse_index(contents: &str) -> ThemeIndex {
+    let mut section = "";
+    let mut directories = Vec::new();
+    let mut inherits = Vec::new();
+    let mut sizes = HashMap::new();
+
+    let list = |value: &str| {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(String::from)
+            .collect::<Vec<_>>()
+    };
+
+    for line in contents.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name;
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+
+        match (section, key.trim()) {
+            ("Icon Theme", "Directories") => directories = list(value),
+            ("Icon Theme", "Inherits") => inherits = list(value),
+            (dir, "Size") => {
+                if let Ok(size) = value.trim().parse::<u32>() {
+                    sizes.insert(dir.to_string(), size);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let dirs = directories
+        .into_iter()
+        .filter_map(|dir| sizes.get(&dir).map(|size| (dir.clone(), *size)))
+        .collect();
+
+    ThemeIndex { inherits, dirs }
+}
+
+/// Subdirectories by how close their size is to `size`, the larger first on ties
+fn closest_dirs(dirs: &[(String, u32)], size: u32) -> Vec<&str> {
+    let mut dirs = dirs.iter().collect::<Vec<_>>();
+    dirs.sort_by_key(|(_, s)| (s.abs_diff(size), std::cmp::Reverse(*s)));
+    dirs.into_iter().map(|(dir, _)| dir.as_str()).collect()
+}
+
+/// Path of the icon, a path is used as is and a name is looked up in the themes and pixmaps
+fn find_icon(name: &str, size: u32, themes: &[Theme], base_dirs: &[PathBuf]) -> Option<PathBuf> {
+    let path = Path::new(name);
+    if path.is_absolute() {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+
+    let names = EXTENSIONS.map(|ext| format!("{name}.{ext}"));
+
+    for theme in themes {
+        for dir in closest_dirs(&theme.index.dirs, size) {
+            for root in &theme.roots {
+                if let Some(file) = names
+                    .iter()
+                    .map(|name| root.join(dir).join(name))
+                    .find(|file| file.is_file())
+                {
+                    return Some(file);
+                }
+            }
+        }
+    }
+
+    // Loose icons, some applications give the file name with its extension
+    let pixmaps = base_dirs
+        .iter()
+        .filter_map(|dir| dir.parent())
+        .map(|dir| dir.join("pixmaps"))
+        .collect::<Vec<_>>();
+    pixmaps.iter().find_map(|dir| {
+        std::iter::once(name)
+            .chain(names.iter().map(String::as_str))
+            .map(|name| dir.join(name))
+            .find(|file| file.is_file())
+    })
+}
+
+/// Decode the file if it's an image, known by its magic bytes or extension, scaled down to
+/// fit in `size` pixels. SVGs are rendered at `size`
+pub fn decode(path: &Path, size: u32) -> Option<ColorImage> {
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("svg"))
+    {
+        return rasterize(path, size);
+    }
+
+    let image = ImageReader::open(path)
+        .ok()?
+        .with_guessed_format()
+        .ok()?
+        .decode()
+        .ok()?;
+    let image = match image.width() > size || image.height() > size {
+        true => image.resize(size, size, FilterType::Triangle),
+        false => image,
+    };
+    let image = image.into_rgba8();
+
+    Some(ColorImage::from_rgba_unmultiplied(
+        [image.width() as usize, image.height() as usize],
+        image.as_raw(),
+    ))
+}
+
+/// Render an SVG to fit in `size` pixels
+fn rasterize(path: &Path, size: u32) -> Option<ColorImage> {
+    let tree = usvg::Tree::from_data(&fs::read(path).ok()?, &usvg::Options::default()).ok()?;
+    let svg_size = tree.size();
+    let scale = size as f32 / svg_size.width().max(svg_size.height());
+
+    let width = (svg_size.width() * scale).round().max(1.0) as u32;
+    let height = (svg_size.height() * scale).round().max(1.0) as u32;
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    Some(ColorImage::from_rgba_premultiplied(
+        [width as usize, height as usize],
+        pixmap.data(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn test_parse_index() {
+        let index = parse_index(
+            "[Icon Theme]
+Name=Papirus
+Inherits=breeze, hicolor
+Directories=16x16/apps,48x48/apps,scalable/apps,missing
+
+[16x16/apps]
+Size=16
+Type=Fixed
+
+[48x48/apps]
+Size=48
+
+[scalable/apps]
+Size=64
+Type=Scalable
+",
+        );
+
+        assert_eq!(
+            index,
+            ThemeIndex {
+                inherits: vec!["breeze".to_string(), "hicolor".to_string()],
+                dirs: vec![
+                    ("16x16/apps".to_string(), 16),
+                    ("48x48/apps".to_string(), 48),
+                    ("scalable/apps".to_string(), 64),
+                ],
+            }
+        );
+    }
+
+    #[rstest]
+    #[case(16, &["16", "32", "48", "64"])]
+    #[case(40, &["48", "32", "64", "16"])]
+    #[case(56, &["64", "48", "32", "16"])]
+    #[case(100, &["64", "48", "32", "16"])]
+    fn test_closest_dirs(#[case] size: u32, #[case] expected: &[&str]) {
+        let dirs = [16, 32, 48, 64].map(|s| (s.to_string(), s));
+
+        assert_eq!(closest_dirs(&dirs, size), expected);
+    }
+
+    #[test]
+    fn test_svg_icon() {
+        let base = env::temp_dir().join(format!("emenu-icons-{}", std::process::id()));
+        let theme = base.join("scalable-theme");
+        fs::create_dir_all(theme.join("scalable/apps")).unwrap();
+        fs::write(
+            theme.join("index.theme"),
+            "[Icon Theme]\nDirectories=scalable/apps\n\n[scalable/apps]\nSize=16\nType=Scalable\n",
+        )
+        .unwrap();
+        fs::write(
+            theme.join("scalable/apps/editor.svg"),
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="8">
+                <rect width="16" height="8" fill="#ff0000"/>
+            </svg>"##,
+        )
+        .unwrap();
+
+        let themes = load_themes("scalable-theme", std::slice::from_ref(&base));
+        let path = find_icon("editor", 32, &themes, std::slice::from_ref(&base));
+        let image = path.as_deref().and_then(|path| decode(path, 32));
+        fs::remove_dir_all(&base).unwrap();
+
+        assert_eq!(path, Some(theme.join("scalable/apps/editor.svg")));
+        let image = image.unwrap();
+        assert_eq!(image.size, [32, 16]);
+        assert_eq!(image.pixels[0], eframe::egui::Color32::RED);
+    }
+}
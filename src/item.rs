@@ -0,0 +1,35 @@
+/// A single entry fed to the matcher
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Item {
+    /// Position of the item in the input
+    pub idx: usize,
+    /// Text that is matched and printed
+    pub text: String,
+    /// Icon name or path
+    pub icon: Option<String>,
+    /// Extra search terms, never shown
+    pub meta: Option<String>,
+    /// Extra data for the mode, like the `info` passed back to scripts, never shown
+    pub info: Option<String>,
+    /// The item is shown but can't be accepted
+    pub nonselectable: bool,
+    /// Highlight the item
+    pub urgent: bool,
+    /// Index of the mode the item comes from
+    pub source: usize,
+}
+
+impl Item {
+    pub fn new(idx: usize, text: String) -> Self {
+        Self {
+            idx,
+            text,
+            ..Default::default()
+        }
+    }
+
+    /// Identifies the item across modes
+    pub fn id(&self) -> (usize, usize) {
+        (self.source, self.idx)
+    }
+}
@@ -0,0 +1,210 @@
+use anyhow::{Context, anyhow, bail};
+use clap::ValueEnum;
+use eframe::egui::{Event, InputState, Key, Modifiers};
+
+/// Actions that can be bound to keys with `--bind`
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum KeyAction {
+    /// Replace the query with the previous one in the history
+    HistoryPrev,
+    /// Replace the query with the next one in the history
+    HistoryNext,
+    /// Mark every match, up to the --multi limit
+    SelectAll,
+    /// Unmark everything
+    DeselectAll,
+    /// Mark the unmarked matches and unmark the marked ones
+    ToggleAll,
+}
+
+impl KeyAction {
+    /// Whether the action changes the marks of --multi
+    pub fn is_mark(self) -> bool {
+        matches!(
+            self,
+            KeyAction::SelectAll | KeyAction::DeselectAll | KeyAction::ToggleAll
+        )
+    }
+
+    /// Whether the action goes through the --history
+    pub fn is_history(self) -> bool {
+        matches!(self, KeyAction::HistoryPrev | KeyAction::HistoryNext)
+    }
+}
+
+/// A key with its exact modifiers, like `ctrl-up`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyBinding {
+    pub modifiers: Modifiers,
+    pub key: Key,
+    pub action: KeyAction,
+}
+
+/// Bindings used unless `--bind` changes them
+pub fn default_bindings() -> Vec<KeyBinding> {
+    vec![
+        KeyBinding {
+            modifiers: Modifiers::CTRL,
+            key: Key::ArrowUp,
+            action: KeyAction::HistoryPrev,
+        },
+        KeyBinding {
+            modifiers: Modifiers::CTRL,
+            key: Key::ArrowDown,
+            action: KeyAction::HistoryNext,
+        },
+        KeyBinding {
+            modifiers: Modifiers::CTRL,
+            key: Key::A,
+            action: KeyAction::SelectAll,
+        },
+        KeyBinding {
+            modifiers: Modifiers::CTRL,
+            key: Key::D,
+            action: KeyAction::DeselectAll,
+        },
+        KeyBinding {
+            modifiers: Modifiers::CTRL,
+            key: Key::T,
+            action: KeyAction::ToggleAll,
+        },
+    ]
+}
+
+/// Parse a `KEY:ACTION` binding, like `ctrl-up:history-prev`
+pub fn parse_binding(binding: &str) -> anyhow::Result<KeyBinding> {
+    let (keys, action) = binding
+        .rsplit_once(':')
+        .ok_or_else(|| anyhow!("Expected KEY:ACTION, found `{binding}`"))?;
+
+    let action = KeyAction::from_str(action, true).map_err(|e| anyhow!(e))?;
+
+    let mut modifiers = Modifiers::NONE;
+    let mut parts = keys.split('-').collect::<Vec<_>>();
+    let key = parts.pop().context("Missing key")?;
+
+    for modifier in parts {
+        modifiers |= match modifier.to_lowercase().as_str() {
+            "ctrl" => Modifiers::CTRL,
+            "alt" => Modifiers::ALT,
+            "shift" => Modifiers::SHIFT,
+            _ => bail!("Unknown modifier `{modifier}`"),
+        };
+    }
+
+    let key = Key::ALL
+        .iter()
+        .copied()
+        .find(|k| k.name().eq_ignore_ascii_case(key))
+        .or_else(|| Key::from_name(key))
+        .ok_or_else(|| anyhow!("Unknown key `{key}`"))?;
+
+    Ok(KeyBinding {
+        modifiers,
+        key,
+        action,
+    })
+}
+
+/// Replace the defaults with the bindings for the same keys
+pub fn merge_bindings(bindings: &[KeyBinding]) -> Vec<KeyBinding> {
+    let mut merged = default_bindings();
+    merged.retain(|d| {
+        !bindings
+            .iter()
+            .any(|b| b.key == d.key && b.modifiers == d.modifiers)
+    });
+    merged.extend_from_slice(bindings);
+    merged
+}
+
+/// Consume the bound keys pressed this frame and return their actions
+pub fn pressed_actions(input: &mut InputState, bindings: &[KeyBinding]) -> Vec<KeyAction> {
+    let mut actions = Vec::new();
+
+    input.events.retain(|event| {
+        let Event::Key {
+            key,
+            modifiers,
+            pressed: true,
+            ..
+        } = event
+        else {
+            return true;
+        };
+
+        match bindings
+            .iter()
+            .find(|b| b.key == *key && modifiers.matches_exact(b.modifiers))
+        {
+            Some(binding) => {
+                actions.push(binding.action);
+                false
+            }
+            None => true,
+        }
+    });
+
+    actions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(
+        "ctrl-up:history-prev",
+        Modifiers::CTRL,
+        Key::ArrowUp,
+        KeyAction::HistoryPrev
+    )]
+    #[case("alt-p:history-prev", Modifiers::ALT, Key::P, KeyAction::HistoryPrev)]
+    #[case("ctrl-shift-n:history-next", Modifiers::CTRL | Modifiers::SHIFT, Key::N, KeyAction::HistoryNext)]
+    #[case(
+        "pagedown:history-next",
+        Modifiers::NONE,
+        Key::PageDown,
+        KeyAction::HistoryNext
+    )]
+    #[case("ctrl-a:select-all", Modifiers::CTRL, Key::A, KeyAction::SelectAll)]
+    #[case("alt-t:toggle-all", Modifiers::ALT, Key::T, KeyAction::ToggleAll)]
+    fn test_parse_binding(
+        #[case] binding: &str,
+        #[case] modifiers: Modifiers,
+        #[case] key: Key,
+        #[case] action: KeyAction,
+    ) {
+        assert_eq!(
+            parse_binding(binding).unwrap(),
+            KeyBinding {
+                modifiers,
+                key,
+                action
+            }
+        );
+    }
+
+    #[test]
+    fn test_merge_bindings() {
+        let bindings = merge_bindings(&[parse_binding("ctrl-a:history-prev").unwrap()]);
+        let ctrl_a = bindings
+            .iter()
+            .filter(|b| b.key == Key::A && b.modifiers == Modifiers::CTRL)
+            .map(|b| b.action)
+            .collect::<Vec<_>>();
+
+        assert_eq!(ctrl_a, vec![KeyAction::HistoryPrev]);
+        assert!(bindings.iter().any(|b| b.action == KeyAction::DeselectAll));
+    }
+
+    #[rstest]
+    #[case("ctrl-up")]
+    #[case("ctrl-up:unknown")]
+    #[case("super-up:history-prev")]
+    #[case("ctrl-nokey:history-prev")]
+    fn test_parse_binding_error(#[case] binding: &str) {
+        assert!(parse_binding(binding).is_err());
+    }
+}
@@ -1,115 +1,363 @@
+use std::ops::Range;
+
+use clap::ValueEnum;
 use eframe::{
     egui::{TextBuffer, TextFormat},
-    epaint::{text::LayoutJob, Color32, FontId},
+    epaint::{text::LayoutJob, Color32},
 };
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Graphemes kept visible next to the outermost highlighted one at a cut end
+const KEEP_AROUND: usize = 5;
+
+const ELLIPSIS: &str = "…";
+
+/// Which part of a match that doesn't fit is replaced with an ellipsis
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum EllipsisMode {
+    /// Cut the start, keeping the first highlighted grapheme visible
+    Start,
+    /// Keep the start and the end
+    Middle,
+    /// Cut the end, keeping the last highlighted grapheme visible
+    End,
+    /// Keep the file name of paths, the highlighted graphemes and as many leading directories
+    /// as fit, like `/home/user/…/main.rs`
+    Smart,
+}
+
+/// Shown before the lines a wrapped match continues on
+const CONTINUATION: &str = "↪";
 
+/// How a match too long for its row is shown
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Overflow {
+    /// Cut, with an ellipsis for each hidden part
+    Cut(EllipsisMode),
+    /// Wrapped over several lines, indented under the first one
+    Wrap,
+}
+
+/// Lay out the pointer and marker `prefix` and a match that fits in `max_width`, measuring the
+/// text with `measure`
 pub fn create_layout(
     input: &str,
     match_str: &str,
-    pointer: &str,
-    marker: &str,
-    max_characters: usize,
-    font_id: FontId,
+    prefix: &str,
+    max_width: f32,
+    overflow: Overflow,
+    format: TextFormat,
+    measure: &mut dyn FnMut(&str) -> f32,
 ) -> LayoutJob {
     let mut layout = LayoutJob::default();
-    let ellipsis = "…";
-    let default_color = Color32::GRAY;
-    let highlight_color = Color32::LIGHT_GREEN;
+    let highlight = TextFormat {
+        color: Color32::LIGHT_GREEN,
+        ..format.clone()
+    };
 
-    let pointer_len = pointer.chars().count();
-    let marker_len = marker.chars().count();
-    let match_str_len = match_str.chars().count();
+    let graphemes = match_str.graphemes(true).collect::<Vec<_>>();
+    let widths = graphemes.iter().map(|g| measure(g)).collect::<Vec<_>>();
+    let highlighted = highlighted_graphemes(&graphemes, &fuzzy_search_highlight(input, match_str));
+    let text = Text {
+        graphemes: &graphemes,
+        highlighted: &highlighted,
+        format: &format,
+        highlight: &highlight,
+    };
 
-    let max_characters = max_characters - pointer_len - marker_len;
+    let prefix_width = measure(prefix);
+    let max_width = max_width - prefix_width;
 
-    let mut start_idx = 0;
-    let mut end_idx = match_str_len.min(max_characters);
+    layout.append(prefix, 0.0, format.clone());
 
-    let mut highlight_indices = fuzzy_search_highlight(input, match_str);
+    match overflow {
+        Overflow::Cut(mode) => {
+            let visible = visible_ranges(
+                &graphemes,
+                &widths,
+                &highlighted,
+                max_width,
+                measure(ELLIPSIS),
+                mode,
+            );
 
-    // figure out start and end indices
-    let keep_chars_on_right = 5;
-    if match_str_len > max_characters && !highlight_indices.is_empty() {
-        let last_highlight = highlight_indices.iter().max().unwrap();
+            let mut shown_end = 0;
+            for visible in visible.into_iter().filter(|r| !r.is_empty()) {
+                if visible.start > shown_end {
+                    layout.append(ELLIPSIS, 0.0, format.clone());
+                }
+                shown_end = visible.end;
+                text.append(&mut layout, visible, 0.0);
+            }
 
-        if last_highlight > &(max_characters - keep_chars_on_right) {
-            end_idx = (last_highlight + keep_chars_on_right).min(match_str_len);
-            start_idx = end_idx - max_characters;
+            if shown_end < graphemes.len() {
+                layout.append(ELLIPSIS, 0.0, format);
+            }
+        }
+        Overflow::Wrap => {
+            let indent = (prefix_width - measure(CONTINUATION)).max(0.0);
+
+            for (n, line) in wrap_lines(&graphemes, &widths, max_width)
+                .into_iter()
+                .enumerate()
+            {
+                let leading_space = match n {
+                    0 => 0.0,
+                    _ => {
+                        layout.append("\n", 0.0, format.clone());
+                        layout.append(CONTINUATION, 0.0, format.clone());
+                        indent
+                    }
+                };
+                text.append(&mut layout, line, leading_space);
+            }
         }
     }
 
-    // dbg!(max_characters);
-    // dbg!(start_idx);
-    // dbg!(end_idx);
-    // dbg!(&highlight_indices);
+    layout
+}
 
-    let start_ellipsis = start_idx > 0;
-    let end_ellipsis = end_idx < match_str_len;
+/// The graphemes of a match with their highlighting
+struct Text<'a> {
+    graphemes: &'a [&'a str],
+    highlighted: &'a [bool],
+    format: &'a TextFormat,
+    highlight: &'a TextFormat,
+}
 
-    if start_ellipsis {
-        start_idx += 1;
+impl Text<'_> {
+    /// Append the graphemes in `range`, a section for each run with the same highlighting
+    fn append(&self, layout: &mut LayoutJob, range: Range<usize>, mut leading_space: f32) {
+        let mut run_start = range.start;
+        for i in range.clone() {
+            if i + 1 == range.end || self.highlighted[i] != self.highlighted[i + 1] {
+                let format = match self.highlighted[i] {
+                    true => self.highlight.clone(),
+                    false => self.format.clone(),
+                };
+                let run = self.graphemes[run_start..=i].concat();
+                layout.append(&run, std::mem::take(&mut leading_space), format);
+                run_start = i + 1;
+            }
+        }
     }
+}
+
+/// Lines of graphemes that fit in `max_width`, broken after whitespace when possible
+fn wrap_lines(graphemes: &[&str], widths: &[f32], max_width: f32) -> Vec<Range<usize>> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let mut width = 0.0;
+    // End of the line when broken after the last whitespace
+    let mut last_break = None;
 
-    if end_ellipsis {
-        end_idx -= 1;
+    let mut i = 0;
+    while i < widths.len() {
+        if width + widths[i] > max_width && i > start {
+            let end = last_break.filter(|&end| end > start).unwrap_or(i);
+            lines.push(start..end);
+            start = end;
+            width = widths[start..i].iter().sum();
+            last_break = None;
+            continue;
+        }
+
+        width += widths[i];
+        if graphemes[i].trim().is_empty() {
+            last_break = Some(i + 1);
+        }
+        i += 1;
     }
 
-    // add pointer and marker
-    layout.append(
-        pointer,
-        0.0,
-        TextFormat::simple(font_id.clone(), default_color),
-    );
-    layout.append(
-        marker,
-        0.0,
-        TextFormat::simple(font_id.clone(), default_color),
-    );
+    lines.push(start..widths.len());
+    lines
+}
 
-    if start_ellipsis {
-        layout.append(
-            ellipsis,
-            0.0,
-            TextFormat::simple(font_id.clone(), default_color),
-        );
+/// Whether each grapheme has a highlighted char
+fn highlighted_graphemes(graphemes: &[&str], char_indices: &[usize]) -> Vec<bool> {
+    let mut char_idx = 0;
+
+    graphemes
+        .iter()
+        .map(|g| {
+            let chars = char_idx..char_idx + g.chars().count();
+            char_idx = chars.end;
+            char_indices.iter().any(|i| chars.contains(i))
+        })
+        .collect()
+}
+
+/// Graphemes shown when the text is cut to fit in `max_width`, an ellipsis replaces each
+/// hidden part
+fn visible_ranges(
+    graphemes: &[&str],
+    widths: &[f32],
+    highlighted: &[bool],
+    max_width: f32,
+    ellipsis: f32,
+    mode: EllipsisMode,
+) -> Vec<Range<usize>> {
+    let whole = 0..widths.len();
+    if widths.iter().sum::<f32>() <= max_width {
+        return vec![whole];
     }
 
-    // Middle text
+    let first_highlight = highlighted.iter().position(|h| *h);
+    let last_highlight = highlighted.iter().rposition(|h| *h);
 
-    let mut cur_idx = start_idx;
-    highlight_indices.sort_unstable();
-    for next_highlight in highlight_indices.into_iter().filter(|x| x >= &start_idx) {
-        if cur_idx < next_highlight {
-            layout.append(
-                match_str.char_range(cur_idx..next_highlight),
-                0.0,
-                TextFormat::simple(font_id.clone(), default_color),
-            );
+    match mode {
+        EllipsisMode::Start => vec![cut_start(widths, first_highlight, max_width, ellipsis)],
+        EllipsisMode::Middle => cut_middle(widths, max_width, ellipsis),
+        EllipsisMode::End => vec![cut_end(widths, last_highlight, max_width, ellipsis)],
+        EllipsisMode::Smart => collapse_path(graphemes, widths, highlighted, max_width, ellipsis)
+            .unwrap_or_else(|| vec![cut_end(widths, last_highlight, max_width, ellipsis)]),
+    }
+}
+
+/// Cut the start, or the end too when the first highlighted grapheme would be hidden
+fn cut_start(
+    widths: &[f32],
+    first_highlight: Option<usize>,
+    max_width: f32,
+    ellipsis: f32,
+) -> Range<usize> {
+    let start = fit_backward(widths, max_width - ellipsis);
+
+    match first_highlight {
+        Some(first) if first < start + KEEP_AROUND => {
+            let start = first.saturating_sub(KEEP_AROUND);
+            let start_ellipsis = if start > 0 { ellipsis } else { 0.0 };
+            let end = start + fit_forward(&widths[start..], max_width - ellipsis - start_ellipsis);
+            start..end
         }
-        cur_idx += (cur_idx..next_highlight).len();
+        _ => start..widths.len(),
+    }
+}
 
-        layout.append(
-            match_str.char_range(cur_idx..cur_idx + 1),
-            0.0,
-            TextFormat::simple(font_id.clone(), highlight_color),
-        );
+/// Keep the start and the end, the end gets half of the width
+fn cut_middle(widths: &[f32], max_width: f32, ellipsis: f32) -> Vec<Range<usize>> {
+    let max_width = max_width - ellipsis;
+    let tail_start = fit_backward(widths, max_width / 2.0);
+    let tail_width = widths[tail_start..].iter().sum::<f32>();
+    let head_end = fit_forward(&widths[..tail_start], max_width - tail_width);
 
-        cur_idx += 1;
+    vec![0..head_end, tail_start..widths.len()]
+}
+
+/// Cut the end, or the start too when the last highlighted grapheme would be hidden
+fn cut_end(
+    widths: &[f32],
+    last_highlight: Option<usize>,
+    max_width: f32,
+    ellipsis: f32,
+) -> Range<usize> {
+    let end = fit_forward(widths, max_width - ellipsis);
+
+    match last_highlight {
+        Some(last) if last + KEEP_AROUND >= end => {
+            let end = (last + 1 + KEEP_AROUND).min(widths.len());
+            let end_ellipsis = if end < widths.len() { ellipsis } else { 0.0 };
+            let start = fit_backward(&widths[..end], max_width - ellipsis - end_ellipsis);
+            start..end
+        }
+        _ => 0..end,
     }
+}
 
-    if cur_idx < end_idx {
-        layout.append(
-            match_str.char_range(cur_idx..end_idx),
-            0.0,
-            TextFormat::simple(font_id.clone(), default_color),
-        );
+/// Keep the file name, the highlighted graphemes and as many leading directories as fit.
+/// `None` when the name and the highlighted graphemes don't fit.
+fn collapse_path(
+    graphemes: &[&str],
+    widths: &[f32],
+    highlighted: &[bool],
+    max_width: f32,
+    ellipsis: f32,
+) -> Option<Vec<Range<usize>>> {
+    // A trailing slash is part of the name of a directory
+    let slashes = graphemes
+        .iter()
+        .enumerate()
+        .filter(|(i, g)| **g == "/" && i + 1 < graphemes.len())
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    // The name starts at its separator, so the ellipsis stays between two slashes
+    let name_start = slashes.last().copied().unwrap_or(0);
+
+    let mut keep = highlighted
+        .iter()
+        .enumerate()
+        .map(|(i, h)| *h || i >= name_start)
+        .collect::<Vec<_>>();
+    let fits = |keep: &[bool]| {
+        let hidden = kept_ranges(&keep.iter().map(|k| !k).collect::<Vec<_>>()).len();
+        let kept_width = widths
+            .iter()
+            .zip(keep)
+            .filter(|(_, k)| **k)
+            .map(|(w, _)| w)
+            .sum::<f32>();
+        kept_width + hidden as f32 * ellipsis <= max_width
+    };
+
+    if !fits(&keep) {
+        return None;
     }
 
-    if end_ellipsis {
-        layout.append(ellipsis, 0.0, TextFormat::simple(font_id, default_color));
+    for slash in slashes {
+        let mut more = keep.clone();
+        more[..=slash].fill(true);
+        if !fits(&more) {
+            break;
+        }
+        keep = more;
     }
 
-    layout
+    Some(kept_ranges(&keep))
+}
+
+/// Ranges of the runs of `true`
+fn kept_ranges(keep: &[bool]) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = None;
+
+    for (i, k) in keep.iter().chain([&false]).enumerate() {
+        match (k, start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                ranges.push(s..i);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+
+    ranges
+}
+
+/// End of the graphemes from the start that fit in `max_width`
+fn fit_forward(widths: &[f32], max_width: f32) -> usize {
+    let mut total = 0.0;
+    widths
+        .iter()
+        .take_while(|w| {
+            total += *w;
+            total <= max_width
+        })
+        .count()
+}
+
+/// Start of the graphemes up to the end that fit in `max_width`
+fn fit_backward(widths: &[f32], max_width: f32) -> usize {
+    let mut total = 0.0;
+    let fitting = widths
+        .iter()
+        .rev()
+        .take_while(|w| {
+            total += *w;
+            total <= max_width
+        })
+        .count();
+    widths.len() - fitting
 }
 
 // TODO: fix this, should split input in words and match better like fzf
@@ -152,6 +400,124 @@ fn fuzzy_search_highlight(search: &str, match_str: &str) -> Vec<usize> {
 mod tests {
     use super::*;
     use rstest::rstest;
+    use unicode_width::UnicodeWidthStr;
+
+    /// Widths in terminal cells
+    fn widths(text: &str) -> Vec<f32> {
+        text.graphemes(true).map(|g| g.width() as f32).collect()
+    }
+
+    #[rstest]
+    #[case("hello", 5.0, None, 0..5)]
+    #[case("hello world", 6.0, None, 0..5)]
+    #[case("日本語のテキスト", 7.0, None, 0..3)]
+    #[case("日本語のテキスト", 8.0, None, 0..3)]
+    #[case("👍🏽👍🏽👍🏽", 4.0, None, 0..1)]
+    #[case("e\u{301}e\u{301}e\u{301}", 3.0, None, 0..3)]
+    #[case("a long match with the highlight at the end", 20.0, Some(30), 18..36)]
+    #[case("highlighted at the start of a long match", 20.0, Some(2), 0..19)]
+    #[case("highlighted at the very end", 10.0, Some(26), 18..27)]
+    fn test_cut_end(
+        #[case] text: &str,
+        #[case] max_width: f32,
+        #[case] last_highlight: Option<usize>,
+        #[case] expected: Range<usize>,
+    ) {
+        let widths = widths(text);
+        let highlighted = (0..widths.len())
+            .map(|i| Some(i) == last_highlight)
+            .collect::<Vec<_>>();
+        let graphemes = text.graphemes(true).collect::<Vec<_>>();
+
+        assert_eq!(
+            visible_ranges(
+                &graphemes,
+                &widths,
+                &highlighted,
+                max_width,
+                1.0,
+                EllipsisMode::End
+            ),
+            vec![expected]
+        );
+    }
+
+    #[rstest]
+    #[case::start("hello world", 6.0, EllipsisMode::Start, &[], vec![6..11])]
+    #[case::start_keeps_highlight("hello world", 6.0, EllipsisMode::Start, &[1], vec![0..5])]
+    #[case::middle("abcdefghij", 6.0, EllipsisMode::Middle, &[], vec![0..3, 8..10])]
+    #[case::smart_not_a_path("hello world", 6.0, EllipsisMode::Smart, &[], vec![0..5])]
+    #[case::smart_path(
+        "/home/user/projects/emenu/src/main.rs",
+        20.0,
+        EllipsisMode::Smart,
+        &[],
+        vec![0..11, 29..37]
+    )]
+    #[case::smart_keeps_highlights(
+        "/home/user/projects/emenu/src/main.rs",
+        20.0,
+        EllipsisMode::Smart,
+        &[14, 21],
+        vec![0..6, 14..15, 21..22, 29..37]
+    )]
+    #[case::smart_directory(
+        "/usr/share/icons/hicolor/48x48/apps/",
+        20.0,
+        EllipsisMode::Smart,
+        &[],
+        vec![0..11, 30..36]
+    )]
+    fn test_visible_ranges(
+        #[case] text: &str,
+        #[case] max_width: f32,
+        #[case] mode: EllipsisMode,
+        #[case] highlights: &[usize],
+        #[case] expected: Vec<Range<usize>>,
+    ) {
+        let graphemes = text.graphemes(true).collect::<Vec<_>>();
+        let highlighted = (0..graphemes.len())
+            .map(|i| highlights.contains(&i))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            visible_ranges(
+                &graphemes,
+                &widths(text),
+                &highlighted,
+                max_width,
+                1.0,
+                mode
+            ),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case("short", 10.0, vec![0..5])]
+    #[case("hello wide world", 10.0, vec![0..6, 6..16])]
+    #[case("abcdefghij", 4.0, vec![0..4, 4..8, 8..10])]
+    #[case("日本語テキスト", 5.0, vec![0..2, 2..4, 4..6, 6..7])]
+    #[case("", 10.0, vec![0..0])]
+    fn test_wrap_lines(
+        #[case] text: &str,
+        #[case] max_width: f32,
+        #[case] expected: Vec<Range<usize>>,
+    ) {
+        let graphemes = text.graphemes(true).collect::<Vec<_>>();
+
+        assert_eq!(wrap_lines(&graphemes, &widths(text), max_width), expected);
+    }
+
+    #[test]
+    fn test_highlighted_graphemes() {
+        let graphemes = "ae\u{301}b".graphemes(true).collect::<Vec<_>>();
+
+        assert_eq!(
+            highlighted_graphemes(&graphemes, &[2, 3]),
+            vec![false, true, true]
+        );
+    }
 
     #[rstest]
     #[case("", "", vec![])]
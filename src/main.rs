@@ -3,80 +3,158 @@
 // hide console window on Windows in release
 // #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use anyhow::{anyhow, ensure};
-use std::{io::stdin, process::exit, sync::Arc, thread, time::Duration};
+use anyhow::anyhow;
+use std::{
+    cmp::Reverse,
+    process::exit,
+    sync::{Arc, PoisonError, RwLock},
+    time::Duration,
+};
 
 use clap::Parser;
+use cli::Layout;
 use eframe::{
-    egui::{self, Event, EventFilter, FontData, Key, Modifiers, Sense, Separator, Vec2},
+    egui::{
+        self, Event, EventFilter, FontData, Key, Modifiers, Pos2, Rect, Sense, Separator, Shape,
+        TextFormat, UiBuilder, Vec2,
+        text::{CCursor, CCursorRange},
+        text_edit::TextEditState,
+    },
     epaint::{Color32, FontId},
 };
-use font_kit::{family_name::FamilyName, source::SystemSource};
-use nucleo::{Nucleo, pattern::Normalization};
+use font_kit::{family_name::FamilyName, handle::Handle, source::SystemSource};
+use nucleo::{Injector, Nucleo, Snapshot, pattern::Normalization};
 
 mod cli;
+mod dmenu;
+mod frecency;
+mod grid;
+mod history;
+mod icon;
+mod item;
+mod keys;
 mod layout;
+mod marks;
+mod mode;
+mod position;
+mod preview;
+
+use frecency::Frecency;
+use grid::{Columns, GridView};
+use history::History;
+use icon::Icons;
+use item::Item;
+use keys::{KeyAction, KeyBinding};
+use layout::{EllipsisMode, Overflow};
+use marks::MultiOrder;
+use mode::{
+    Action, Combi, CombiInjectors, Mode, ModeKind, Sink, calc::Calc, drun::Drun, emoji::Emoji,
+    files::Files, run::Run, script::Script, ssh::Ssh, stdin::Stdin,
+};
+use position::{Placement, Screen, Size};
+use preview::Previews;
+
+/// Fonts used for the symbols missing in the main font, when they are installed
+const SYMBOL_FONTS: [&str; 5] = [
+    "Noto Sans Symbols",
+    "Noto Sans Symbols 2",
+    "Noto Sans Math",
+    "DejaVu Sans",
+    "Symbola",
+];
+
+/// Part of the width taken by the image preview, right of the list
+const PREVIEW_WIDTH: f32 = 0.4;
+
+/// Matches measured to find the widest one with `--columns auto`
+const MEASURED_MATCHES: u32 = 1000;
 
-// TODO: proper theme, config, multimode, highlight searched in matches
+/// Best matches sorted again with the frecency boost, the others keep nucleo's order
+const RANKED_MATCHES: u32 = 1000;
+
+// TODO: proper theme, config, highlight searched in matches
 fn main() -> Result<(), eframe::Error> {
-    let cli = cli::Cli::parse();
+    let args = std::env::args().collect::<Vec<_>>();
+    let compat = dmenu::is_compat(&args);
+    let mut cli = match compat {
+        true => cli::Cli::parse_from(dmenu::translate_args(args)),
+        false => cli::Cli::parse_from(args),
+    };
+    // Also set when invoked as `dmenu`, without the flag
+    cli.dmenu_compat = compat;
+
+    if cli.mode.is_empty() {
+        cli.mode.push(match cli.script {
+            Some(_) => ModeKind::Script,
+            None => ModeKind::Stdin,
+        });
+    }
 
-    if cli.exit_if_empty && atty::is(atty::Stream::Stdin) {
+    if cli.exit_if_empty && cli.mode == [ModeKind::Stdin] && atty::is(atty::Stream::Stdin) {
         exit(0)
     }
 
-    let border_color = match color_from_string(&cli.border_color) {
-        Ok(border_color) => border_color,
+    let theme = match Theme::from_cli(&cli) {
+        Ok(theme) => theme,
         Err(e) => {
             eprintln!("{e}");
             exit(1);
         }
     };
 
-    let nucleo = Nucleo::new(
-        {
-            let mut conf = nucleo::Config::DEFAULT;
-            conf.ignore_case = cli.case_insensitive;
-            conf.normalize = !cli.literal;
-            conf
-        },
-        Arc::new(|| {}),
-        None,
-        1,
-    );
-
-    let inj = nucleo.injector();
-
-    // Read from stdin in another thread
-    // TODO: nucleo has to add support for --tac
-    thread::spawn(move || {
-        if atty::isnt(atty::Stream::Stdin) {
-            stdin()
-                .lines()
-                .map_while(Result::ok)
-                .enumerate()
-                .for_each(|item| {
-                    inj.push(item, |(_i, s), row| {
-                        row[0] = s.clone().into();
-                    });
-                })
+    let mut tabs = cli
+        .mode
+        .iter()
+        .map(|kind| Tab::new(*kind, &cli))
+        .collect::<Vec<_>>();
+
+    let combi_injectors = CombiInjectors::new(RwLock::new(combi_injectors(&tabs)));
+
+    // The password prompt doesn't list any items
+    if !cli.password {
+        for (i, tab) in tabs.iter_mut().enumerate() {
+            tab.load(i, &combi_injectors);
         }
-    });
+    }
 
-    let window_height = cli.window_height;
-    let window_width = cli.window_width;
+    let screen = position::find_screen(cli.monitor);
+
+    // A guess until the monitor is known on the first frame
+    let monitor_size = screen.map_or(Vec2::new(1920.0, 1080.0), |s| s.monitor.size());
+    let window_height = match cli.lines {
+        Some(lines) => lines_height(lines, cli.font_size),
+        None => cli.window_height.resolve(monitor_size.y),
+    };
+    let window_width = cli.window_width.resolve(monitor_size.x);
+    let window_size = Vec2::new(window_width, window_height);
+
+    let placement = Placement {
+        position: cli.position,
+        offset: Vec2::new(cli.x_offset, cli.y_offset),
+        screen,
+    };
+
+    let mut viewport = egui::ViewportBuilder::default()
+        .with_active(true)
+        .with_always_on_top()
+        .with_decorations(false)
+        .with_resizable(false)
+        .with_taskbar(false)
+        .with_drag_and_drop(false)
+        .with_inner_size([window_width, window_height]);
+
+    // The height of the rows is only known once the fonts are loaded
+    if cli.lines.is_none() && screen.is_some() {
+        viewport = viewport.with_max_inner_size((window_width, window_height));
+    }
+
+    if let Some(pos) = placement.window_pos(window_size) {
+        viewport = viewport.with_position(pos);
+    }
 
     let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_active(true)
-            .with_always_on_top()
-            .with_decorations(false)
-            .with_resizable(false)
-            .with_taskbar(false)
-            .with_drag_and_drop(false)
-            .with_inner_size([window_width, window_height])
-            .with_max_inner_size((window_width, window_height)),
-        centered: true,
+        viewport,
+        centered: screen.is_none(),
         ..Default::default()
     };
 
@@ -86,14 +164,18 @@ fn main() -> Result<(), eframe::Error> {
         Box::new(|cc| {
             let ctx = &cc.egui_ctx;
 
-            if let Some(font_family) = cli.font.clone() {
-                let font_data = get_font_data(&font_family)
-                    .map_err(|e| {
-                        eprintln!("Error loading the font `{font_family}`: {e}");
-                        exit(1);
-                    })
-                    .unwrap();
-                let mut fonts = egui::FontDefinitions::default();
+            let mut fonts = egui::FontDefinitions::default();
+
+            // Fonts earlier in the list are tried first, egui's own fonts are the last fallback
+            let mut loaded = 0;
+            for font_family in &cli.font {
+                let font_data = match get_font_data(font_family) {
+                    Ok(font_data) => font_data,
+                    Err(e) => {
+                        eprintln!("Couldn't load the font `{font_family}`, skipping it: {e}");
+                        continue;
+                    }
+                };
                 fonts
                     .font_data
                     .insert(font_family.clone(), font_data.into());
@@ -102,78 +184,406 @@ fn main() -> Result<(), eframe::Error> {
                     .families
                     .entry(egui::FontFamily::Monospace)
                     .or_default()
-                    .insert(0, font_family.clone());
+                    .insert(loaded, font_family.clone());
+                loaded += 1;
+            }
 
-                ctx.set_fonts(fonts);
+            // The default fonts only have some of the symbols, use the installed ones as fallback
+            if cli.mode.contains(&ModeKind::Emoji) {
+                for font_family in SYMBOL_FONTS {
+                    let Ok(font_data) = get_font_data(font_family) else {
+                        continue;
+                    };
+                    fonts
+                        .font_data
+                        .insert(font_family.to_string(), font_data.into());
+
+                    fonts
+                        .families
+                        .entry(egui::FontFamily::Monospace)
+                        .or_default()
+                        .push(font_family.to_string());
+                }
             }
 
+            ctx.set_fonts(fonts);
+
             // ctx.set_pixels_per_point(1.225);
             // ctx.set_fonts(FontDefinitions::)
-            let font = egui::FontId {
-                // size: 13.0,
-                size: cli.font_size,
-                family: egui::FontFamily::Monospace,
-            };
             ctx.set_global_style(egui::style::Style {
-                override_font_id: Some(font.clone()),
+                override_font_id: Some(FontId::monospace(cli.font_size)),
                 ..Default::default()
             });
 
-            Ok(Box::new(Emenu::new(nucleo, cli, font, border_color)))
+            Ok(Box::new(Emenu::new(
+                ctx,
+                tabs,
+                combi_injectors,
+                cli,
+                theme,
+                placement,
+                window_size,
+            )))
         }),
     )
 }
 
-struct Emenu {
+/// Every mode also pushes its items to the combi mode through these
+fn combi_injectors(tabs: &[Tab]) -> Vec<Injector<Item>> {
+    tabs.iter()
+        .filter(|tab| tab.is_combi)
+        .map(|tab| tab.nucleo.injector())
+        .collect()
+}
+
+/// A mode with its own matcher and query
+struct Tab {
+    mode: Box<dyn Mode>,
+    is_combi: bool,
+    nucleo: Nucleo<Item>,
     input: String,
-    nucleo: Nucleo<(usize, String)>,
+    selected_idx: u32,
+    first_idx: u32,
+    output: Vec<Item>,
+    /// Positions of the best matches sorted with the frecency boost, empty without it
+    order: Vec<u32>,
+    /// Width of the widest match for `--columns auto`, measured again when the matches change
+    widest_match: Option<f32>,
+    matcher: nucleo::Matcher,
+}
+
+impl Tab {
+    fn new(kind: ModeKind, cli: &cli::Cli) -> Self {
+        let mut conf = nucleo::Config::DEFAULT;
+        conf.ignore_case = cli.case_insensitive;
+        conf.normalize = !cli.literal;
+
+        let nucleo = Nucleo::new(conf.clone(), Arc::new(|| {}), None, 1);
+
+        Self {
+            mode: create_mode(kind, cli),
+            is_combi: kind == ModeKind::Combi,
+            nucleo,
+            input: Default::default(),
+            selected_idx: 0,
+            first_idx: 0,
+            output: Default::default(),
+            order: Default::default(),
+            widest_match: None,
+            matcher: nucleo::Matcher::new(conf),
+        }
+    }
+
+    /// Sort the best matches by their score plus the frecency boost
+    fn rank(&mut self, frecency: &Frecency) {
+        let snap = self.nucleo.snapshot();
+        let mut scored = (0..snap.matched_item_count().min(RANKED_MATCHES))
+            .filter_map(|n| {
+                let item = snap.get_matched_item(n)?;
+                let score = snap
+                    .pattern()
+                    .score(item.matcher_columns, &mut self.matcher)
+                    .unwrap_or(0);
+                Some((n, score + frecency.boost(&item.data.text)))
+            })
+            .collect::<Vec<_>>();
+
+        // Stable, so equal scores keep nucleo's order
+        scored.sort_by_key(|&(_, score)| Reverse(score));
+        self.order = scored.into_iter().map(|(n, _)| n).collect();
+    }
+
+    /// Load the items of the mode, pushed to the combi tabs too unless it's one
+    fn load(&mut self, source: usize, combi: &CombiInjectors) {
+        let combi = match self.is_combi {
+            true => Default::default(),
+            false => combi.clone(),
+        };
+        self.mode
+            .load(Sink::new(source, self.nucleo.injector(), combi));
+    }
+
+    /// Match the items against the current input
+    fn reparse(&mut self) {
+        self.nucleo.pattern.reparse(
+            0,
+            &self.input,
+            nucleo::pattern::CaseMatching::Smart,
+            Normalization::Smart,
+            false,
+        );
+
+        // Clear the first_idx and selected_idx on new input
+        self.first_idx = 0;
+        self.selected_idx = 0;
+    }
+}
+
+fn create_mode(kind: ModeKind, cli: &cli::Cli) -> Box<dyn Mode> {
+    match kind {
+        ModeKind::Stdin => Box::new(Stdin {
+            custom_entries: cli.dmenu_compat,
+        }),
+        ModeKind::Run => Box::new(Run),
+        ModeKind::Drun => Box::new(Drun::default()),
+        ModeKind::Ssh => Box::new(Ssh {
+            connect: cli.ssh_connect,
+        }),
+        ModeKind::Files => Box::new(Files::new(cli.dir.clone(), cli.hidden, cli.max_depth)),
+        ModeKind::Calc => Box::new(Calc::new()),
+        ModeKind::Emoji => Box::new(Emoji {
+            codepoint: cli.codepoint,
+        }),
+        #[cfg(not(target_os = "windows"))]
+        ModeKind::Window => Box::new(mode::window::Window::new().unwrap_or_else(|e| {
+            eprintln!("{e}");
+            exit(1);
+        })),
+        #[cfg(target_os = "windows")]
+        ModeKind::Window => {
+            eprintln!("The window mode is only available on X11");
+            exit(1);
+        }
+        ModeKind::Combi => Box::new(Combi),
+        ModeKind::Script => {
+            let Some(path) = cli.script.clone() else {
+                eprintln!("The script mode needs a --script");
+                exit(1);
+            };
+
+            let script = Script::new(path).unwrap_or_else(|e| {
+                eprintln!("{e}");
+                exit(1);
+            });
+
+            if script.is_empty() && cli.exit_if_empty {
+                exit(0)
+            }
+
+            Box::new(script)
+        }
+    }
+}
+
+struct Emenu {
+    tabs: Vec<Tab>,
+    current: usize,
+    /// Injectors of the combi tabs, replaced when a mode reloads
+    combi_injectors: CombiInjectors,
+    history: Option<History>,
+    frecency: Option<Frecency>,
+    bindings: Vec<KeyBinding>,
+    /// Move the cursor to the end of the input on the next frame
+    cursor_to_end: bool,
+    mode_names: Vec<String>,
     prompt: String,
     marker: String,
     pointer: String,
-    border_color: Color32,
+    theme: Theme,
     cycle: bool,
     scroll_offset: u32,
     exit_lost_focus: bool,
+    password: bool,
+    layout: Layout,
+    columns: Option<Columns>,
+    ellipsis_mode: EllipsisMode,
+    wrap: bool,
+    hover_select: bool,
+    multi_order: MultiOrder,
+    lines: Option<u32>,
+    auto_height: bool,
+    /// Height for --auto-height without --lines
+    max_height: f32,
+    window_width: Size,
+    window_height: Size,
+    placement: Placement,
+    /// The screen found with X11 is in pixels until the scale is known on the first frame
+    screen_in_pixels: bool,
+    /// Size the window was last given
+    window_size: Vec2,
     has_focus: bool,
-    selected_idx: u32,
-    first_idx: u32,
     output_number: usize,
-    output: Vec<(usize, String)>,
     font_id: FontId,
+    /// Shown left of the items with --show-icons
+    icons: Option<Icons>,
+    /// Thumbnail of the selected image with --image-preview
+    previews: Option<Previews>,
 }
 
 impl Emenu {
     fn new(
-        nucleo: Nucleo<(usize, String)>,
+        ctx: &egui::Context,
+        tabs: Vec<Tab>,
+        combi_injectors: CombiInjectors,
         cli: cli::Cli,
-        font_id: FontId,
-        border_color: Color32,
+        theme: Theme,
+        placement: Placement,
+        window_size: Vec2,
     ) -> Self {
+        // Decoded at the size of the rows in pixels
+        let icons = cli.show_icons.then(|| {
+            let size = (cli.font_size * 1.25 * ctx.pixels_per_point()).ceil() as u32;
+            Icons::new(ctx.clone(), cli.icon_theme.clone(), size)
+        });
+
+        // Without --multi the marking keys are left to the input, like ctrl-a to select all
+        let mut bindings = keys::merge_bindings(&cli.bind);
+        if cli.multi.is_none() {
+            bindings.retain(|binding| !binding.action.is_mark());
+        }
+        // Same for the history keys without --history, like ctrl-n to move down
+        if cli.history.is_none() {
+            bindings.retain(|binding| !binding.action.is_history());
+        }
+
         Self {
-            nucleo,
+            mode_names: tabs.iter().map(|tab| tab.mode.name().to_string()).collect(),
+            combi_injectors,
+            tabs,
+            current: 0,
+            history: cli
+                .history
+                .map(|path| History::load(path, cli.history_size)),
+            frecency: cli.frecency_db.map(Frecency::load),
+            bindings,
+            cursor_to_end: false,
             prompt: cli.prompt,
             marker: cli.marker,
             pointer: cli.pointer,
             cycle: cli.cycle,
             scroll_offset: cli.scroll_offset,
             exit_lost_focus: cli.exit_lost_focus,
+            password: cli.password,
+            layout: cli.layout,
+            columns: cli.columns,
+            ellipsis_mode: cli.ellipsis_mode,
+            wrap: cli.wrap,
+            hover_select: cli.hover_select,
+            multi_order: cli.multi_order,
+            lines: cli.lines,
+            auto_height: cli.auto_height,
+            max_height: window_size.y,
+            window_width: cli.window_width,
+            window_height: cli.window_height,
+            screen_in_pixels: placement.screen.is_some(),
+            placement,
+            window_size,
             has_focus: false,
-            input: Default::default(),
-            selected_idx: 0,
-            first_idx: 0,
-            border_color,
+            theme,
             output_number: cli.multi.unwrap_or(1),
-            output: Default::default(),
-            font_id,
+            font_id: FontId::monospace(cli.font_size),
+            icons,
+            previews: cli.image_preview.then(|| Previews::new(ctx.clone())),
         }
     }
 }
 
 impl eframe::App for Emenu {
+    fn clear_color(&self, _visuals: &egui::Visuals) -> [f32; 4] {
+        self.theme
+            .background
+            // eframe's default
+            .unwrap_or(Color32::from_rgba_unmultiplied(12, 12, 12, 180))
+            .to_normalized_gamma_f32()
+    }
+
     fn ui(&mut self, ui: &mut egui::Ui, _frame: &mut eframe::Frame) {
-        let snapshot_changed = self.nucleo.tick(10).changed;
+        // Without X11 the monitor is only known now, assume it starts at the origin
+        if self.placement.screen.is_none()
+            && let Some(monitor_size) = ui.input(|i| i.viewport().monitor_size)
+        {
+            self.placement.screen = Some(Screen {
+                monitor: Rect::from_min_size(Pos2::ZERO, monitor_size),
+                pointer: None,
+            });
+            self.fit_monitor(ui.ctx());
+        }
+
+        // The window is placed and sized in points, so the sizes from X11 are scaled down
+        if self.screen_in_pixels
+            && let Some(pixels_per_point) = ui.input(|i| i.viewport().native_pixels_per_point)
+        {
+            self.screen_in_pixels = false;
+            self.placement.screen = self
+                .placement
+                .screen
+                .map(|screen| screen.to_points(pixels_per_point));
+            self.fit_monitor(ui.ctx());
+        }
+
+        // Switch modes with ctrl + tab and ctrl + shift + tab
+        if ui.input(|i| i.modifiers.matches_exact(Modifiers::CTRL) && i.key_pressed(Key::Tab)) {
+            self.current = (self.current + 1) % self.tabs.len();
+        }
+        if ui.input(|i| {
+            i.modifiers
+                .matches_exact(Modifiers::CTRL | Modifiers::SHIFT)
+                && i.key_pressed(Key::Tab)
+        }) {
+            self.current = (self.current + self.tabs.len() - 1) % self.tabs.len();
+        }
+
+        let actions = ui.input_mut(|i| keys::pressed_actions(i, &self.bindings));
+        for action in actions {
+            let tab = &mut self.tabs[self.current];
+            let snap = tab.nucleo.snapshot();
+            let matches =
+                (0..snap.matched_item_count()).filter_map(|n| nth_match(snap, &tab.order, n));
+
+            let query = match (action, &mut self.history) {
+                (KeyAction::HistoryPrev, Some(history)) => history.prev(&tab.input),
+                (KeyAction::HistoryNext, Some(history)) => history.next(),
+                (KeyAction::SelectAll, _) => {
+                    marks::mark(&mut tab.output, matches, self.output_number);
+                    None
+                }
+                (KeyAction::DeselectAll, _) => {
+                    tab.output.clear();
+                    None
+                }
+                (KeyAction::ToggleAll, _) => {
+                    marks::toggle_all(&mut tab.output, matches, self.output_number);
+                    None
+                }
+                _ => None,
+            };
+
+            if let Some(query) = query {
+                tab.input = query.to_string();
+                tab.reparse();
+                self.cursor_to_end = true;
+            }
+        }
+
+        // Let the mode handle its own keys
+        let keys = ui.input(|i| {
+            i.events
+                .iter()
+                .filter_map(|e| match e {
+                    Event::Key {
+                        key,
+                        pressed: true,
+                        modifiers,
+                        ..
+                    } => Some((*key, *modifiers)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        });
+        for (key, modifiers) in keys {
+            let tab = &mut self.tabs[self.current];
+            let action = tab.mode.key_pressed(key, modifiers, &tab.input);
+            self.handle_action(self.current, action);
+        }
+
+        let tab = &mut self.tabs[self.current];
+        let snapshot_changed = tab.nucleo.tick(10).changed;
 
         if snapshot_changed {
+            tab.widest_match = None;
+            if let Some(frecency) = &self.frecency {
+                tab.rank(frecency);
+            }
             ui.request_repaint_after(Duration::from_secs(1));
         }
 
@@ -182,38 +592,89 @@ impl eframe::App for Emenu {
         // ctx.fonts(|f| dbg!(f.pixels_per_point()));
 
         let inner_margin = 8.0;
+        let outer_margin = 4.0;
+        let horizontal = self.layout == Layout::Horizontal;
+        let input_id = egui::Id::new("input");
+
+        // Left/right go through the matches, unless they move the cursor in the input
+        let (mut step_prev, mut step_next) = (false, false);
+        if horizontal || self.columns.is_some() {
+            let tab = &self.tabs[self.current];
+            let cursor = TextEditState::load(ui.ctx(), input_id)
+                .and_then(|state| state.cursor.char_range())
+                .map(|range| range.primary.index);
+            let cursor_at_end = cursor.is_none_or(|c| c == tab.input.chars().count());
+
+            step_next =
+                cursor_at_end && ui.input_mut(|i| i.consume_key(Modifiers::NONE, Key::ArrowRight));
+            step_prev = cursor_at_end
+                && tab.first_idx + tab.selected_idx > 0
+                && ui.input_mut(|i| i.consume_key(Modifiers::NONE, Key::ArrowLeft));
+        }
+
+        // Height taken by everything but the list, its row height and how many rows it could show
+        let mut list_size = None;
+
+        let main_layout = match self.layout {
+            Layout::Default | Layout::ReverseList => egui::Layout::bottom_up(egui::Align::Min),
+            Layout::Reverse | Layout::Horizontal => egui::Layout::top_down(egui::Align::Min),
+        };
 
         egui::CentralPanel::default()
             .frame(
                 egui::Frame::default()
-                    .stroke((0.5, self.border_color))
+                    .stroke((0.5, self.theme.border))
                     .inner_margin(inner_margin)
-                    .outer_margin(4.0)
+                    .outer_margin(outer_margin)
                     .corner_radius(2.0),
             )
             .show_inside(ui, |ui| {
-                ui.vertical(|ui| {
+                ui.with_layout(main_layout, |ui| {
+                    let tab = &mut self.tabs[self.current];
+
+                    // Space left of the input row for the horizontal layout
+                    let mut matches_rect = None;
+
                     ui.horizontal(|ui| {
-                        let prompt = if !self.prompt.is_empty() {
+                        let prompt = tab.mode.prompt().unwrap_or(&self.prompt);
+                        let prompt = if !prompt.is_empty() {
                             Some(ui.add_sized(
                                 // Use available height to center label
                                 [ui.available_height(), 0.0],
-                                egui::Label::new(&self.prompt),
+                                egui::Label::new(prompt),
                             ))
                         } else {
                             None
                         };
 
+                        let edit_size = match horizontal {
+                            true => Vec2::new(ui.available_width() / 3.0, ui.available_height()),
+                            false => ui.available_size(),
+                        };
                         let mut edit = ui.add_sized(
-                            ui.available_size(),
-                            egui::TextEdit::singleline(&mut self.input)
+                            edit_size,
+                            egui::TextEdit::singleline(&mut tab.input)
+                                .id(input_id)
+                                .password(self.password)
                                 .vertical_align(egui::Align::Center),
                         );
 
+                        if horizontal {
+                            matches_rect = Some(ui.available_rect_before_wrap());
+                        }
+
                         if let Some(prompt) = prompt {
                             edit = edit.labelled_by(prompt.id);
                         }
 
+                        if std::mem::take(&mut self.cursor_to_end)
+                            && let Some(mut state) = TextEditState::load(ui.ctx(), edit.id)
+                        {
+                            let end = CCursor::new(tab.input.chars().count());
+                            state.cursor.set_char_range(Some(CCursorRange::one(end)));
+                            state.store(ui.ctx(), edit.id);
+                        }
+
                         // Prevent other widgets from taking focus
                         edit.request_focus();
 
@@ -233,28 +694,51 @@ impl eframe::App for Emenu {
                         if ui.input(|i| {
                             i.modifiers.matches_exact(Modifiers::CTRL) && i.key_pressed(Key::K)
                         }) {
-                            self.input.clear();
+                            tab.input.clear();
                             edit.mark_changed();
                         }
 
                         if edit.changed() {
-                            self.nucleo.pattern.reparse(
-                                0,
-                                &self.input,
-                                nucleo::pattern::CaseMatching::Smart,
-                                Normalization::Smart,
-                                false,
-                            );
-
-                            // Clear the first_idx and selected_idx on new input
-                            self.first_idx = 0;
-                            self.selected_idx = 0;
+                            tab.reparse();
                         }
                     });
 
-                    ui.add_space(8.0);
+                    // Print whatever was typed, it's never saved to the history
+                    if self.password {
+                        if ui.input(|i| i.key_pressed(Key::Enter)) {
+                            print!("{}", tab.input);
+                            exit(0);
+                        }
+                        return;
+                    }
+
+                    if let Some(message) = tab.mode.message() {
+                        ui.add_space(4.0);
+                        ui.label(message);
+                    }
+
+                    if !horizontal {
+                        ui.add_space(8.0);
+                    }
 
-                    let snap = self.nucleo.snapshot();
+                    // Mode bar, click to switch
+                    if self.tabs.len() > 1 {
+                        ui.horizontal(|ui| {
+                            for (i, tab) in self.tabs.iter().enumerate() {
+                                if ui
+                                    .selectable_label(i == self.current, tab.mode.name())
+                                    .clicked()
+                                {
+                                    self.current = i;
+                                }
+                            }
+                        });
+
+                        ui.add_space(4.0);
+                    }
+
+                    let tab = &mut self.tabs[self.current];
+                    let snap = tab.nucleo.snapshot();
                     let total_count = snap.item_count();
                     let matched_count = snap.matched_item_count();
 
@@ -265,7 +749,7 @@ impl eframe::App for Emenu {
                         if self.output_number > 1 {
                             format!(
                                 "({}{})",
-                                self.output.len(),
+                                tab.output.len(),
                                 if self.output_number < 999999999 {
                                     format!("/{}", self.output_number)
                                 } else {
@@ -277,79 +761,326 @@ impl eframe::App for Emenu {
                         }
                     );
 
-                    let count_label = ui.horizontal(|ui| {
-                        let count_label = ui.label(&count_string);
-                        ui.add(
-                            Separator::default()
-                                .horizontal()
-                                .spacing(count_label.rect.height()),
-                        );
-                        count_label
-                    });
+                    let count_size = ui
+                        .painter()
+                        .layout_no_wrap(count_string.clone(), self.font_id.clone(), Color32::GRAY)
+                        .size();
+
+                    // The horizontal layout has no room for the counter
+                    if !horizontal {
+                        ui.horizontal(|ui| {
+                            ui.label(&count_string);
+                            ui.add(Separator::default().horizontal().spacing(count_size.y));
+                        });
 
-                    ui.add_space(4.0);
+                        ui.add_space(4.0);
+                    }
 
-                    // Get the width of a single char to truncate the matched items
-                    let char_width = count_label.inner.rect.width() / (count_string.len() as f32);
-                    let char_height = count_label.inner.rect.height();
+                    let char_height = count_size.y;
+                    let spacing = ui.spacing().item_spacing;
+                    let row_height = char_height + spacing.y;
+                    let icon_width = match self.icons {
+                        Some(_) => char_height + spacing.x,
+                        None => 0.0,
+                    };
+
+                    // Keep the right of the list for the preview
+                    let list_area = ui.available_rect_before_wrap();
+                    let preview_width = match self.previews {
+                        Some(_) if !horizontal => (list_area.width() * PREVIEW_WIDTH).round(),
+                        _ => 0.0,
+                    };
+                    ui.set_max_width(list_area.width() - preview_width);
+
+                    // Columns of the grid, the widest match decides how many fit with auto
+                    let list_width = ui.available_width();
+                    let columns = match (self.columns, horizontal) {
+                        (Some(Columns::Fixed(n)), false) => n,
+                        (Some(Columns::Auto), false) => {
+                            let widest = match tab.widest_match {
+                                Some(widest) => widest,
+                                None => {
+                                    let widest = (0..matched_count.min(MEASURED_MATCHES))
+                                        .filter_map(|n| nth_match(snap, &tab.order, n))
+                                        .map(|item| text_width(ui, &self.font_id, &item.text))
+                                        .fold(0.0, f32::max);
+                                    tab.widest_match = Some(widest);
+                                    widest
+                                }
+                            };
+                            let cell_width = widest
+                                + text_width(ui, &self.font_id, &self.pointer)
+                                + text_width(ui, &self.font_id, &self.marker)
+                                + icon_width
+                                + spacing.x;
+                            ((list_width + spacing.x) / cell_width).max(1.0) as u32
+                        }
+                        _ => 1,
+                    };
+                    let grid = columns > 1;
+                    let cell_width =
+                        (list_width - (columns - 1) as f32 * spacing.x) / columns as f32;
+                    let grid_rows = ((ui.available_height() + spacing.y) / row_height) as u32;
 
                     let mut view_rows: u32 = 0;
+                    // Lines the shown matches were wrapped on, past their first one
+                    let mut wrapped_lines: u32 = 0;
+                    let overflow = match self.wrap && !grid && !horizontal {
+                        true => Overflow::Wrap,
+                        false => Overflow::Cut(self.ellipsis_mode),
+                    };
+                    let mut accepted = ui.input(|i| i.key_pressed(Key::Enter));
+                    let live_result = tab.mode.live_result(&tab.input);
+
+                    list_size = Some((
+                        ui.ctx().content_rect().height() - ui.available_height(),
+                        row_height,
+                        if live_result.is_some() {
+                            1
+                        } else {
+                            matched_count.div_ceil(columns)
+                        },
+                    ));
 
-                    ui.vertical(|ui| {
-                        for (i, matched) in snap
-                            .matched_items(self.first_idx..snap.matched_item_count())
-                            .enumerate()
-                        {
-                            if ui.available_height() < char_height {
-                                break;
-                            }
+                    let mut draw_list = |ui: &mut egui::Ui| {
+                        // The mode shows its result instead of the matches
+                        if let Some(result) = &live_result {
+                            let layout = layout::create_layout(
+                                "",
+                                result,
+                                &format!(
+                                    "{}{}",
+                                    self.pointer,
+                                    " ".repeat(self.marker.chars().count())
+                                ),
+                                ui.available_width(),
+                                Overflow::Cut(self.ellipsis_mode),
+                                TextFormat::simple(self.font_id.clone(), self.theme.text),
+                                &mut |text| text_width(ui, &self.font_id, text),
+                            );
+                            ui.add(
+                                egui::Label::new(layout).wrap_mode(egui::TextWrapMode::Truncate),
+                            );
+                            return;
+                        }
 
-                            view_rows += 1;
+                        let mut draw_matches = |ui: &mut egui::Ui| {
+                            for (i, item) in (tab.first_idx..matched_count)
+                                .filter_map(|n| nth_match(snap, &tab.order, n))
+                                .enumerate()
+                            {
+                                if grid && i as u32 >= grid_rows * columns {
+                                    break;
+                                }
+                                if !grid && ui.available_height() < char_height {
+                                    break;
+                                }
 
-                            let (match_id, match_string) = matched.data;
+                                let pointer = if i == tab.selected_idx as usize {
+                                    self.pointer.clone()
+                                } else {
+                                    " ".repeat(self.pointer.chars().count())
+                                };
 
-                            let pointer = if i == self.selected_idx as usize {
-                                self.pointer.clone()
-                            } else {
-                                " ".repeat(self.pointer.chars().count())
-                            };
+                                let is_marked = tab.output.iter().any(|o| o.id() == item.id());
+                                let mut marker = if is_marked {
+                                    self.marker.clone()
+                                } else {
+                                    " ".repeat(self.marker.chars().count())
+                                };
+
+                                // Clicking the marker toggles the mark
+                                let marker_start =
+                                    icon_width + text_width(ui, &self.font_id, &pointer);
+                                let marker_end =
+                                    marker_start + text_width(ui, &self.font_id, &marker);
+
+                                // Label the items of the combi mode with their source
+                                if tab.is_combi {
+                                    marker
+                                        .push_str(&format!("[{}] ", self.mode_names[item.source]));
+                                }
 
-                            let marker = if self.output.iter().any(|(i, _s)| i == match_id) {
-                                self.marker.clone()
-                            } else {
-                                " ".repeat(self.pointer.chars().count())
-                            };
+                                let max_width = match grid {
+                                    true => cell_width,
+                                    false => ui.available_width(),
+                                } - icon_width;
+
+                                let is_selected = i == tab.selected_idx as usize;
+                                let text_color = if item.nonselectable {
+                                    Color32::DARK_GRAY
+                                } else if item.urgent {
+                                    Color32::LIGHT_RED
+                                } else if is_selected && let Some(color) = self.theme.selected_text
+                                {
+                                    color
+                                } else {
+                                    self.theme.text
+                                };
+
+                                let layout = layout::create_layout(
+                                    &tab.input,
+                                    &item.text,
+                                    &format!("{pointer}{marker}"),
+                                    max_width,
+                                    overflow,
+                                    TextFormat::simple(self.font_id.clone(), text_color),
+                                    &mut |text| text_width(ui, &self.font_id, text),
+                                );
+                                let galley = ui.painter().layout_job(layout);
+
+                                // Only the first match is truncated to fit in the line
+                                if horizontal
+                                    && i > 0
+                                    && ui.available_width() < galley.size().x + icon_width
+                                {
+                                    break;
+                                }
 
-                            // TODO: get the correct amount of characters that fit
-                            // let max_chars = get_max_chars_in_ui(ui, char_width, inner_margin)
-                            //     - (marker_len + pointer_len);
-                            let max_chars = get_max_chars_in_ui(ui, char_width, inner_margin);
+                                // Wrapped matches are only shown whole, but at least one is
+                                if overflow == Overflow::Wrap
+                                    && i > 0
+                                    && ui.available_height() < galley.size().y
+                                {
+                                    break;
+                                }
 
-                            let layout = layout::create_layout(
-                                &self.input,
-                                match_string,
-                                &pointer,
-                                &marker,
-                                max_chars,
-                                self.font_id.clone(),
-                            );
+                                view_rows += 1;
+                                wrapped_lines += galley.rows.len().saturating_sub(1) as u32;
+
+                                // Painted behind the label once its size is known
+                                let background = ui.painter().add(Shape::Noop);
 
-                            let entry = ui.add(
-                                egui::Label::new(layout)
+                                let label = egui::Label::new(galley)
                                     .sense(Sense::click())
-                                    .wrap_mode(egui::TextWrapMode::Truncate),
-                            );
+                                    .wrap_mode(match overflow {
+                                        Overflow::Wrap => egui::TextWrapMode::Wrap,
+                                        Overflow::Cut(_) => egui::TextWrapMode::Truncate,
+                                    });
+
+                                // Blank when the item has no icon or it's still loading
+                                let icon = self.icons.as_mut().map(|icons| {
+                                    item.icon
+                                        .as_deref()
+                                        .and_then(|name| icons.get(name))
+                                        .cloned()
+                                });
+                                let add_row = |ui: &mut egui::Ui| {
+                                    let size = Vec2::splat(char_height);
+                                    let icon = icon.map(|icon| match icon {
+                                        Some(texture) => ui.add(
+                                            egui::Image::from_texture(&texture)
+                                                .fit_to_exact_size(size)
+                                                .sense(Sense::click()),
+                                        ),
+                                        None => ui.allocate_exact_size(size, Sense::click()).1,
+                                    });
+                                    let label = ui.add(label);
+                                    match icon {
+                                        Some(icon) => icon.union(label),
+                                        None => label,
+                                    }
+                                };
+
+                                let entry = match grid {
+                                    true => {
+                                        ui.allocate_ui_with_layout(
+                                            Vec2::new(cell_width, char_height),
+                                            egui::Layout::left_to_right(egui::Align::Center),
+                                            |ui| {
+                                                ui.set_min_width(cell_width);
+                                                add_row(ui)
+                                            },
+                                        )
+                                        .inner
+                                    }
+                                    false if self.icons.is_some() => ui.horizontal(add_row).inner,
+                                    false => add_row(ui),
+                                };
+
+                                if is_selected && let Some(color) = self.theme.selected_background {
+                                    let row = match horizontal || grid {
+                                        true => entry.rect,
+                                        false => entry.rect.with_max_x(ui.max_rect().right()),
+                                    };
+                                    ui.painter()
+                                        .set(background, Shape::rect_filled(row, 0.0, color));
+                                }
+
+                                // Only when the mouse moves, so it doesn't undo the keys
+                                if self.hover_select
+                                    && entry.hovered()
+                                    && ui.input(|i| i.pointer.is_moving())
+                                {
+                                    tab.selected_idx = i as u32;
+                                }
+
+                                let multi = self.output_number > 1;
+                                let modifiers = ui.input(|i| i.modifiers);
+                                let on_marker = entry.interact_pointer_pos().is_some_and(|pos| {
+                                    (marker_start..marker_end)
+                                        .contains(&(pos.x - entry.rect.left()))
+                                });
+
+                                // Shift marks everything from the selected match to the clicked one
+                                if multi && entry.clicked() && modifiers.shift {
+                                    let selected = tab.first_idx + tab.selected_idx;
+                                    let clicked = tab.first_idx + i as u32;
+                                    let range = selected.min(clicked)..=selected.max(clicked);
+                                    marks::mark(
+                                        &mut tab.output,
+                                        range.filter_map(|n| nth_match(snap, &tab.order, n)),
+                                        self.output_number,
+                                    );
+                                } else if multi
+                                    && (entry.secondary_clicked()
+                                        || (entry.clicked() && (modifiers.ctrl || on_marker)))
+                                {
+                                    marks::toggle(&mut tab.output, item, self.output_number);
+                                }
 
-                            if entry.clicked() {
-                                self.selected_idx = i as u32;
+                                if entry.clicked() || entry.secondary_clicked() {
+                                    tab.selected_idx = i as u32;
+                                }
+
+                                if entry.double_clicked() {
+                                    tab.selected_idx = i as u32;
+                                    if multi {
+                                        marks::mark(&mut tab.output, [item], self.output_number);
+                                    }
+                                    accepted = true;
+                                }
                             }
+                        };
 
-                            if entry.double_clicked() && self.output_number == 1 {
-                                print!("{match_string}");
-                                exit(0);
+                        match grid {
+                            true => {
+                                ui.horizontal_wrapped(draw_matches);
                             }
+                            false => draw_matches(ui),
                         }
-                    });
+                    };
+
+                    match (matches_rect, self.layout) {
+                        (Some(rect), _) => draw_list(
+                            &mut ui.new_child(
+                                UiBuilder::new()
+                                    .max_rect(rect)
+                                    .layout(egui::Layout::left_to_right(egui::Align::Center)),
+                            ),
+                        ),
+                        // The grid always fills its rows downward
+                        (None, Layout::Default) if !grid => {
+                            ui.with_layout(egui::Layout::bottom_up(egui::Align::Min), draw_list);
+                        }
+                        (None, _) => {
+                            ui.vertical(draw_list);
+                        }
+                    }
+
+                    if let Some((_, _, rows)) = &mut list_size {
+                        *rows += wrapped_lines;
+                    }
 
                     // Move current pointer with ctrl + p/n, arrows or mouse wheel
                     let tab_forward = self.output_number > 1
@@ -364,18 +1095,13 @@ impl eframe::App for Emenu {
                     // If multi, toggle before moving
                     if (tab_forward || tab_backward)
                         && let Some(item) =
-                            snap.get_matched_item(self.first_idx + self.selected_idx)
+                            nth_match(snap, &tab.order, tab.first_idx + tab.selected_idx)
                     {
-                        if let Some(pos) = self.output.iter().position(|(i, _s)| i == &item.data.0)
-                        {
-                            self.output.remove(pos);
-                        } else if self.output.len() < self.output_number {
-                            self.output.push(item.data.clone())
-                        }
+                        marks::toggle(&mut tab.output, item, self.output_number);
                     }
 
                     // limit offset if its too big
-                    let scroll_offset = self.scroll_offset.min(view_rows - 1);
+                    let scroll_offset = self.scroll_offset.min(view_rows.saturating_sub(1));
                     let raw_scroll_delta = ui.input(|i| {
                         i.events
                             .iter()
@@ -394,67 +1120,311 @@ impl eframe::App for Emenu {
                         (i.modifiers.matches_exact(Modifiers::CTRL) && i.key_pressed(Key::N))
                             || i.key_pressed(Key::ArrowDown)
                     });
-                    if view_rows > 0 && (pressed_down || scrolled_down || tab_forward) {
-                        if self.selected_idx >= (view_rows - 1 - scroll_offset)
-                            && self.first_idx + view_rows < matched_count
-                        {
-                            self.first_idx += 1;
-                        } else if self.cycle && self.selected_idx == view_rows.saturating_sub(1) {
-                            self.selected_idx = 0;
-                            self.first_idx = 0;
-                        } else {
-                            self.selected_idx = self.selected_idx.saturating_add(1);
-                        }
-                    }
-
                     let scrolled_up = ui.ui_contains_pointer() && raw_scroll_delta > 0.0;
                     let pressed_up = ui.input(|i| {
                         (i.modifiers.matches_exact(Modifiers::CTRL) && i.key_pressed(Key::P))
                             || i.key_pressed(Key::ArrowUp)
                     });
-                    if view_rows > 0 && (pressed_up || scrolled_up || tab_backward) {
-                        if self.first_idx != 0 && self.selected_idx <= scroll_offset {
-                            self.first_idx -= 1;
-                            self.selected_idx += 1;
+
+                    // The default layout lists the matches upward, so up goes to the next one
+                    let (move_next, move_prev) = match (self.layout, grid) {
+                        (Layout::Default, false) => {
+                            (pressed_up || scrolled_up, pressed_down || scrolled_down)
                         }
+                        _ => (pressed_down || scrolled_down, pressed_up || scrolled_up),
+                    };
+
+                    // Up and down move by rows of the grid, left and right by matches
+                    if grid {
+                        let delta = if move_next {
+                            columns as i64
+                        } else if move_prev {
+                            -(columns as i64)
+                        } else if step_next || tab_forward {
+                            1
+                        } else if step_prev || tab_backward {
+                            -1
+                        } else {
+                            0
+                        };
 
-                        if self.cycle && self.selected_idx == 0 {
-                            self.selected_idx = matched_count;
-                            self.first_idx = matched_count - view_rows;
+                        if delta != 0 {
+                            let view = GridView {
+                                columns,
+                                rows: grid_rows,
+                                scroll_offset: self.scroll_offset,
+                                cycle: self.cycle,
+                            };
+                            (tab.first_idx, tab.selected_idx) =
+                                view.step(tab.first_idx, tab.selected_idx, delta, matched_count);
                         }
+                    } else {
+                        let move_next = move_next || step_next;
+                        let move_prev = move_prev || step_prev;
+
+                        if view_rows > 0 && (move_next || tab_forward) {
+                            if tab.selected_idx >= (view_rows - 1 - scroll_offset)
+                                && tab.first_idx + view_rows < matched_count
+                            {
+                                tab.first_idx += 1;
+                            } else if self.cycle && tab.selected_idx == view_rows.saturating_sub(1)
+                            {
+                                tab.selected_idx = 0;
+                                tab.first_idx = 0;
+                            } else {
+                                tab.selected_idx = tab.selected_idx.saturating_add(1);
+                            }
+                        }
+
+                        if view_rows > 0 && (move_prev || tab_backward) {
+                            if tab.first_idx != 0 && tab.selected_idx <= scroll_offset {
+                                tab.first_idx -= 1;
+                                tab.selected_idx += 1;
+                            }
 
-                        self.selected_idx = self.selected_idx.saturating_sub(1);
+                            if self.cycle && tab.selected_idx == 0 {
+                                tab.selected_idx = matched_count;
+                                tab.first_idx = matched_count - view_rows;
+                            }
+
+                            tab.selected_idx = tab.selected_idx.saturating_sub(1);
+                        }
+                    }
+
+                    // Wrapped matches change how many fit, scroll until the selection shows
+                    if overflow == Overflow::Wrap
+                        && view_rows > 0
+                        && tab.selected_idx >= view_rows
+                        && tab.first_idx + view_rows < matched_count
+                    {
+                        let hidden = tab.selected_idx + 1 - view_rows;
+                        tab.first_idx += hidden;
+                        tab.selected_idx -= hidden;
+                        ui.request_repaint();
                     }
 
                     // Prevent the selected_idx from overflowing
-                    self.selected_idx = self.selected_idx.min(view_rows.saturating_sub(1));
+                    tab.selected_idx = tab.selected_idx.min(view_rows.saturating_sub(1));
+
+                    // Scaled down to fit the preview, centered in it
+                    if preview_width > 0.0
+                        && let Some(previews) = &mut self.previews
+                        && let Some(path) =
+                            nth_match(snap, &tab.order, tab.first_idx + tab.selected_idx)
+                                .and_then(|item| tab.mode.path(item))
+                        && let Some(texture) = previews.get(&path)
+                    {
+                        let area = list_area
+                            .with_min_x(list_area.right() - preview_width)
+                            .shrink2(Vec2::new(spacing.x, 0.0));
+                        let size = texture.size_vec2();
+                        let scale = (area.width() / size.x).min(area.height() / size.y).min(1.0);
+                        let rect = Rect::from_center_size(area.center(), size * scale);
+                        let uv = Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0));
+                        ui.painter().image(texture.id(), rect, uv, Color32::WHITE);
+                    }
 
                     // Handle enter
-                    if ui.input(|i| i.key_pressed(Key::Enter)) {
-                        if total_count == 0 {
-                            print!("{}", self.input)
-                        } else if self.output_number > 1 {
-                            print!(
-                                "{}",
-                                self.output
-                                    .iter()
-                                    .map(|(_i, s)| s.as_str())
-                                    .collect::<Vec<_>>()
-                                    .join("\n")
-                            )
-                        } else if let Some(item) =
-                            snap.get_matched_item(self.first_idx + self.selected_idx)
-                        {
-                            print!("{}", item.data.1)
+                    if accepted {
+                        let selected =
+                            nth_match(snap, &tab.order, tab.first_idx + tab.selected_idx)
+                                .filter(|_| live_result.is_none())
+                                .cloned();
+
+                        if selected.as_ref().is_some_and(|item| item.nonselectable) {
+                            return;
+                        }
+
+                        if total_count != 0 && self.output_number > 1 {
+                            save_history(self.history.as_mut(), &tab.input);
+                            save_frecency(self.frecency.as_mut(), &tab.output);
+                            let matches =
+                                (0..matched_count).filter_map(|n| nth_match(snap, &tab.order, n));
+                            let marked = marks::sorted(&tab.output, self.multi_order, matches)
+                                .into_iter()
+                                .cloned()
+                                .collect::<Vec<_>>();
+                            self.accept_marks(&marked);
+                            return;
+                        }
+
+                        if selected.is_none() && total_count != 0 && !tab.mode.custom_entries() {
+                            exit(0);
                         }
-                        exit(0);
+
+                        self.accept(selected);
                     }
                 })
             });
+
+        if let Some((used_height, row_height, rows)) = list_size {
+            self.fit_height(ui.ctx(), used_height, row_height, rows);
+        }
     }
 }
 
 impl Emenu {
+    /// Resize the window to show --lines rows, or only the matches with --auto-height
+    fn fit_height(&mut self, ctx: &egui::Context, used_height: f32, row_height: f32, rows: u32) {
+        let horizontal = self.layout == Layout::Horizontal;
+        if self.lines.is_none() && !self.auto_height && !horizontal {
+            return;
+        }
+
+        let max_rows = self
+            .lines
+            .unwrap_or_else(|| ((self.max_height - used_height) / row_height).max(0.0) as u32);
+        // The horizontal layout is a single line, the matches are next to the input
+        let rows = match (horizontal, self.auto_height) {
+            (true, _) => 0,
+            (false, true) => rows.min(max_rows),
+            (false, false) => max_rows,
+        };
+
+        let height = (used_height + rows as f32 * row_height).ceil();
+        if self.window_size.y != height {
+            self.resize(ctx, Vec2::new(self.window_size.x, height));
+        }
+    }
+
+    /// Size the window relative to the monitor and place it again
+    fn fit_monitor(&mut self, ctx: &egui::Context) {
+        let Some(screen) = self.placement.screen else {
+            return;
+        };
+        let monitor_size = screen.monitor.size();
+
+        if self.lines.is_none() {
+            self.max_height = self.window_height.resolve(monitor_size.y);
+        }
+        let width = self.window_width.resolve(monitor_size.x);
+        let height = self.max_height;
+        self.resize(ctx, Vec2::new(width, height));
+    }
+
+    /// Resize the window, keeping it at its position on the monitor
+    fn resize(&mut self, ctx: &egui::Context, size: Vec2) {
+        self.window_size = size;
+        ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(size));
+
+        if let Some(pos) = self.placement.window_pos(size) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::OuterPosition(pos));
+        }
+    }
+
+    /// Let the mode the entry comes from handle it
+    fn accept(&mut self, selected: Option<Item>) {
+        let source = selected.as_ref().map_or(self.current, |item| item.source);
+        let input = self.tabs[self.current].input.clone();
+
+        let action = self.tabs[source]
+            .mode
+            .accept(selected.as_ref(), &input)
+            .unwrap_or_else(|e| {
+                eprintln!("{e}");
+                exit(1);
+            });
+
+        if !matches!(action, Action::Ignore) {
+            save_history(self.history.as_mut(), &input);
+            save_frecency(self.frecency.as_mut(), selected.as_slice());
+        }
+
+        self.handle_action(source, action);
+    }
+
+    /// Let the modes the marked items come from handle them, keeping their order
+    fn accept_marks(&mut self, marked: &[Item]) {
+        let mut printed = Vec::new();
+        let mut last_action = None;
+
+        // The combi tab mixes the modes, each one gets its consecutive items
+        for items in marked.chunk_by(|a, b| a.source == b.source) {
+            let source = items[0].source;
+            let action = self.tabs[source]
+                .mode
+                .accept_many(items)
+                .unwrap_or_else(|e| {
+                    eprintln!("{e}");
+                    exit(1);
+                });
+
+            match action {
+                Action::Print(text) => printed.push(text),
+                Action::Ignore => {}
+                action => last_action = Some((source, action)),
+            }
+        }
+
+        if !printed.is_empty() {
+            self.handle_action(self.current, Action::Print(printed.join("\n")));
+        }
+        if let Some((source, action)) = last_action {
+            self.handle_action(source, action);
+        }
+    }
+
+    fn handle_action(&mut self, source: usize, action: Action) {
+        match action {
+            Action::Print(text) => {
+                print!("{text}");
+                exit(0);
+            }
+            Action::Exit => exit(0),
+            Action::Reload => {
+                self.refill_combi(source);
+
+                // The new items reuse the indexes, the marks would point at other ones
+                for tab in &mut self.tabs {
+                    tab.output.retain(|item| item.source != source);
+                }
+
+                let tab = &mut self.tabs[source];
+                tab.nucleo.restart(true);
+                tab.load(source, &self.combi_injectors);
+                tab.input.clear();
+                tab.reparse();
+
+                self.current = source;
+            }
+            Action::Ignore => {}
+        }
+    }
+
+    /// Restart the combi tabs without the items of the reloading mode, the other modes keep
+    /// pushing to them
+    fn refill_combi(&mut self, source: usize) {
+        let mut combi = self
+            .combi_injectors
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        if combi.is_empty() {
+            return;
+        }
+
+        for tab in self.tabs.iter_mut().filter(|tab| tab.is_combi) {
+            tab.nucleo.restart(true);
+            tab.reparse();
+        }
+        *combi = combi_injectors(&self.tabs);
+
+        // The modes can't always load again, like stdin, so the items they pushed are copied.
+        // The sinks wait for the lock, the ones they push next go to the new injectors
+        for (i, tab) in self.tabs.iter().enumerate() {
+            if i == source || tab.is_combi {
+                continue;
+            }
+            let items = tab.nucleo.injector();
+            for n in 0..items.injected_items() {
+                if let Some(item) = items.get(n) {
+                    for injector in combi.iter() {
+                        mode::inject(injector, item.data.clone());
+                    }
+                }
+            }
+        }
+    }
+
     fn keyboard_events_exit(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         // exit on ctrl+c or esc
         if ctx.input(|i| i.modifiers.matches_exact(Modifiers::CTRL) && i.key_pressed(Key::C)) {
@@ -477,24 +1447,86 @@ impl Emenu {
     }
 }
 
-fn get_font_data(font_name: &str) -> anyhow::Result<FontData> {
-    let font = SystemSource::new()
-        .select_best_match(
-            &[FamilyName::Title(font_name.to_string())],
-            &font_kit::properties::Properties::default(),
-        )?
-        .load()?;
+fn save_history(history: Option<&mut History>, query: &str) {
+    if let Some(history) = history
+        && let Err(e) = history.save(query)
+    {
+        eprintln!("Failed to save the history: {e}");
+    }
+}
+
+fn save_frecency(frecency: Option<&mut Frecency>, picked: &[Item]) {
+    let Some(frecency) = frecency else {
+        return;
+    };
 
-    ensure!(font.is_monospace(), "Font is not monospaced");
+    for item in picked {
+        frecency.record(&item.text);
+    }
+
+    if let Err(e) = frecency.save() {
+        eprintln!("Failed to save the frecency database: {e}");
+    }
+}
+
+/// Nth match, following the frecency ranking when there is one
+fn nth_match<'a>(snap: &'a Snapshot<Item>, order: &[u32], n: u32) -> Option<&'a Item> {
+    let n = order.get(n as usize).copied().unwrap_or(n);
+    snap.get_matched_item(n).map(|item| item.data)
+}
+
+fn get_font_data(font_name: &str) -> anyhow::Result<FontData> {
+    let handle = SystemSource::new().select_best_match(
+        &[FamilyName::Title(font_name.to_string())],
+        &font_kit::properties::Properties::default(),
+    )?;
+    // The font can be one face of a `.ttc` collection
+    let index = match handle {
+        Handle::Path { font_index, .. } | Handle::Memory { font_index, .. } => font_index,
+    };
+    let font = handle.load()?;
 
     let font_data = font.copy_font_data().ok_or(anyhow!("No font data"))?;
 
-    Ok(FontData::from_owned((*font_data).clone()))
+    Ok(FontData {
+        index,
+        ..FontData::from_owned((*font_data).clone())
+    })
 }
 
-fn get_max_chars_in_ui(ui: &mut egui::Ui, char_width: f32, inner_margin: f32) -> usize {
-    // let char_width = ui.fonts(|f| f.glyph_width(font_id, ' '));
-    ((ui.max_rect().width() - inner_margin * 2.0) / char_width).round() as usize
+/// Width of the text in points, from the widths of its glyphs
+fn text_width(ui: &egui::Ui, font_id: &FontId, text: &str) -> f32 {
+    ui.ctx()
+        .fonts_mut(|f| text.chars().map(|c| f.glyph_width(font_id, c)).sum())
+}
+
+/// Colors set from the command line
+struct Theme {
+    border: Color32,
+    background: Option<Color32>,
+    text: Color32,
+    selected_background: Option<Color32>,
+    selected_text: Option<Color32>,
+}
+
+impl Theme {
+    fn from_cli(cli: &cli::Cli) -> anyhow::Result<Self> {
+        let optional = |color: &Option<String>| color.as_deref().map(color_from_string).transpose();
+
+        Ok(Self {
+            border: color_from_string(&cli.border_color)?,
+            background: optional(&cli.background_color)?,
+            text: color_from_string(&cli.text_color)?,
+            selected_background: optional(&cli.selected_background_color)?,
+            selected_text: optional(&cli.selected_text_color)?,
+        })
+    }
+}
+
+/// Rough height of the window showing `lines` rows, until the rows can be measured
+fn lines_height(lines: u32, font_size: f32) -> f32 {
+    let row_height = font_size * 1.25 + 3.0;
+    (lines + 2) as f32 * row_height + 40.0
 }
 
 fn color_from_string(color: &str) -> anyhow::Result<Color32> {
@@ -548,10 +1580,14 @@ fn color_from_string(color: &str) -> anyhow::Result<Color32> {
         return Ok(c);
     }
 
-    // Hex colors: "#RRGGBB" or "RRGGBB"
+    // Hex colors: "#RRGGBB" or "RRGGBB", and the short "#RGB"
     let hex = color.strip_prefix('#').unwrap_or(&color);
+    let hex = match hex.len() {
+        3 => hex.chars().flat_map(|c| [c, c]).collect(),
+        _ => hex.to_string(),
+    };
     if hex.len() == 6
-        && let Ok(rgb) = u32::from_str_radix(hex, 16)
+        && let Ok(rgb) = u32::from_str_radix(&hex, 16)
     {
         let r = ((rgb >> 16) & 0xFF) as u8;
         let g = ((rgb >> 8) & 0xFF) as u8;
@@ -0,0 +1,170 @@
+use std::collections::{HashMap, HashSet};
+
+use clap::ValueEnum;
+
+use crate::item::Item;
+
+/// Order the marked items are printed in
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum MultiOrder {
+    /// The order they were read in
+    Input,
+    /// The order they were marked in
+    Selection,
+    /// The order of the matches, the ones that don't match the query last
+    Score,
+}
+
+/// Mark the item, or unmark it when it's already marked. Nothing is marked past `limit`.
+pub fn toggle(marks: &mut Vec<Item>, item: &Item, limit: usize) {
+    match marks.iter().position(|m| m.id() == item.id()) {
+        Some(pos) => {
+            marks.remove(pos);
+        }
+        None if marks.len() < limit => marks.push(item.clone()),
+        None => {}
+    }
+}
+
+/// Mark the items that aren't marked yet, up to `limit` marks
+pub fn mark<'a>(marks: &mut Vec<Item>, items: impl IntoIterator<Item = &'a Item>, limit: usize) {
+    let mut marked = marks.iter().map(Item::id).collect::<HashSet<_>>();
+
+    for item in items {
+        if marks.len() >= limit {
+            break;
+        }
+        if marked.insert(item.id()) {
+            marks.push(item.clone());
+        }
+    }
+}
+
+/// Unmark the marked items and mark the others, up to `limit` marks
+pub fn toggle_all<'a>(
+    marks: &mut Vec<Item>,
+    items: impl IntoIterator<Item = &'a Item>,
+    limit: usize,
+) {
+    let items = items.into_iter().collect::<Vec<_>>();
+    let ids = items.iter().map(|item| item.id()).collect::<HashSet<_>>();
+    let marked = marks.iter().map(Item::id).collect::<HashSet<_>>();
+
+    marks.retain(|m| !ids.contains(&m.id()));
+    mark(
+        marks,
+        items
+            .into_iter()
+            .filter(|item| !marked.contains(&item.id())),
+        limit,
+    );
+}
+
+/// The marks in `order`, `matches` are the current matches from the best one
+pub fn sorted<'a>(
+    marks: &'a [Item],
+    order: MultiOrder,
+    matches: impl IntoIterator<Item = &'a Item>,
+) -> Vec<&'a Item> {
+    let mut sorted = marks.iter().collect::<Vec<_>>();
+
+    match order {
+        MultiOrder::Input => sorted.sort_by_key(|item| (item.source, item.idx)),
+        MultiOrder::Selection => {}
+        MultiOrder::Score => {
+            let ranks = matches
+                .into_iter()
+                .enumerate()
+                .map(|(rank, item)| (item.id(), rank))
+                .collect::<HashMap<_, _>>();
+            sorted.sort_by_key(|item| ranks.get(&item.id()).copied().unwrap_or(usize::MAX));
+        }
+    }
+
+    sorted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn items(texts: &[&str]) -> Vec<Item> {
+        texts
+            .iter()
+            .enumerate()
+            .map(|(i, text)| Item::new(i, text.to_string()))
+            .collect()
+    }
+
+    fn texts(marks: &[Item]) -> Vec<&str> {
+        marks.iter().map(|item| item.text.as_str()).collect()
+    }
+
+    #[rstest]
+    #[case::marks(&["a"], 1, 10, &["a", "b"])]
+    #[case::unmarks(&["a", "b"], 0, 10, &["b"])]
+    #[case::stops_at_limit(&["a", "c"], 1, 2, &["a", "c"])]
+    #[case::unmarks_at_limit(&["a", "b"], 1, 2, &["a"])]
+    fn test_toggle(
+        #[case] marked: &[&str],
+        #[case] item: usize,
+        #[case] limit: usize,
+        #[case] expected: &[&str],
+    ) {
+        let all = items(&["a", "b", "c"]);
+        let mut marks = all
+            .iter()
+            .filter(|item| marked.contains(&item.text.as_str()))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        toggle(&mut marks, &all[item], limit);
+
+        assert_eq!(texts(&marks), expected);
+    }
+
+    #[rstest]
+    #[case::inverts(10, &["d", "a", "c"])]
+    #[case::stops_at_limit(2, &["d", "a"])]
+    fn test_toggle_all(#[case] limit: usize, #[case] expected: &[&str]) {
+        let all = items(&["a", "b", "c", "d"]);
+        // `d` was marked before the query filtered it out
+        let mut marks = vec![all[3].clone(), all[1].clone()];
+
+        toggle_all(&mut marks, &all[..3], limit);
+
+        assert_eq!(texts(&marks), expected);
+    }
+
+    #[rstest]
+    #[case::input(MultiOrder::Input, &["a", "b", "c", "d"])]
+    #[case::selection(MultiOrder::Selection, &["c", "a", "d", "b"])]
+    #[case::score(MultiOrder::Score, &["b", "c", "a", "d"])]
+    fn test_sorted(#[case] order: MultiOrder, #[case] expected: &[&str]) {
+        let all = items(&["a", "b", "c", "d"]);
+        let marks = [2, 0, 3, 1].map(|i| all[i].clone());
+        // `d` doesn't match the query anymore
+        let matches = [&all[1], &all[2], &all[0]];
+
+        assert_eq!(
+            sorted(&marks, order, matches)
+                .into_iter()
+                .map(|item| item.text.as_str())
+                .collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case::skips_marked(10, &["b", "a", "c", "d"])]
+    #[case::stops_at_limit(3, &["b", "a", "c"])]
+    fn test_mark(#[case] limit: usize, #[case] expected: &[&str]) {
+        let all = items(&["a", "b", "c", "d"]);
+        let mut marks = vec![all[1].clone()];
+
+        mark(&mut marks, &all, limit);
+
+        assert_eq!(texts(&marks), expected);
+    }
+}
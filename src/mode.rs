@@ -0,0 +1,209 @@
+use std::{
+    env,
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use anyhow::Context;
+use clap::ValueEnum;
+use eframe::egui::{Key, Modifiers};
+use nucleo::Injector;
+
+use crate::item::Item;
+
+pub mod calc;
+pub mod drun;
+pub mod emoji;
+pub mod files;
+pub mod run;
+pub mod script;
+pub mod ssh;
+pub mod stdin;
+#[cfg(not(target_os = "windows"))]
+pub mod window;
+
+/// Sources of items that can be shown at the same time
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum ModeKind {
+    /// Lines read from stdin
+    Stdin,
+    /// Executables in `$PATH`
+    Run,
+    /// Applications from `.desktop` files
+    Drun,
+    /// Items from the `--script` script
+    Script,
+    /// Hosts from `~/.ssh/config` and `~/.ssh/known_hosts`
+    Ssh,
+    /// File browser, starting in `DIR`
+    Files,
+    /// Calculator that evaluates the input
+    Calc,
+    /// Emoji and Unicode characters, searched by name
+    Emoji,
+    /// X11 windows, activates the one picked
+    Window,
+    /// All the other modes merged together
+    Combi,
+}
+
+/// What to do after an entry is accepted
+pub enum Action {
+    /// Print the text and exit
+    Print(String),
+    /// Exit without printing anything
+    Exit,
+    /// Reload the items of the mode and clear the input
+    Reload,
+    /// Keep the menu open as it is
+    Ignore,
+}
+
+pub trait Mode {
+    /// Name shown in the mode bar
+    fn name(&self) -> &str;
+
+    /// Push the items of the mode, this is called again after [`Action::Reload`]
+    fn load(&mut self, sink: Sink);
+
+    /// Handle an accepted entry, `selected` is `None` when nothing matched the `input`
+    fn accept(&mut self, selected: Option<&Item>, input: &str) -> anyhow::Result<Action>;
+
+    /// Handle the entries marked with --multi. Each one is accepted, the texts to print are
+    /// joined by lines
+    fn accept_many(&mut self, items: &[Item]) -> anyhow::Result<Action> {
+        let mut printed = Vec::new();
+        let mut action = Action::Ignore;
+
+        for item in items {
+            match self.accept(Some(item), &item.text)? {
+                Action::Print(text) => printed.push(text),
+                Action::Ignore => {}
+                // Reloading wins over exiting, the mode has more to show
+                Action::Exit if matches!(action, Action::Reload) => {}
+                other => action = other,
+            }
+        }
+
+        Ok(match printed.is_empty() {
+            true => action,
+            false => Action::Print(printed.join("\n")),
+        })
+    }
+
+    /// Handle a key press before the input does, `input` is the query of the mode
+    fn key_pressed(&mut self, _key: Key, _modifiers: Modifiers, _input: &str) -> Action {
+        Action::Ignore
+    }
+
+    /// Text shown instead of the matches for the current input
+    fn live_result(&self, _input: &str) -> Option<String> {
+        None
+    }
+
+    /// Whether the input can be accepted when no item matches it
+    fn custom_entries(&self) -> bool {
+        true
+    }
+
+    /// Prompt that replaces `--prompt`
+    fn prompt(&self) -> Option<&str> {
+        None
+    }
+
+    /// Message shown under the input
+    fn message(&self) -> Option<&str> {
+        None
+    }
+
+    /// File the item stands for, previewed with --image-preview when it's an image
+    fn path(&self, _item: &Item) -> Option<PathBuf> {
+        None
+    }
+}
+
+/// Injectors of the combi matchers, shared by every sink and replaced when they restart
+pub type CombiInjectors = Arc<RwLock<Vec<Injector<Item>>>>;
+
+/// Pushes the items of a mode to its own matcher and to the combi matcher
+#[derive(Clone)]
+pub struct Sink {
+    /// Index of the mode the items come from
+    source: usize,
+    injector: Injector<Item>,
+    combi: CombiInjectors,
+}
+
+impl Sink {
+    pub fn new(source: usize, injector: Injector<Item>, combi: CombiInjectors) -> Self {
+        Self {
+            source,
+            injector,
+            combi,
+        }
+    }
+
+    pub fn push(&self, mut item: Item) {
+        item.source = self.source;
+        // Held while pushing, so a restart of the combi matchers doesn't miss the item
+        let combi = self.combi.read().unwrap_or_else(PoisonError::into_inner);
+        for injector in std::iter::once(&self.injector).chain(combi.iter()) {
+            inject(injector, item.clone());
+        }
+    }
+}
+
+/// Push the item to a matcher, it's matched against its text and meta
+pub fn inject(injector: &Injector<Item>, item: Item) {
+    injector.push(item, |item, row| {
+        row[0] = match &item.meta {
+            Some(meta) => format!("{} {meta}", item.text).into(),
+            None => item.text.clone().into(),
+        };
+    });
+}
+
+/// Matches the items of every other mode, which handle the accepted entries
+pub struct Combi;
+
+impl Mode for Combi {
+    fn name(&self) -> &str {
+        "combi"
+    }
+
+    fn load(&mut self, _sink: Sink) {}
+
+    fn accept(&mut self, _selected: Option<&Item>, input: &str) -> anyhow::Result<Action> {
+        Ok(Action::Print(input.to_string()))
+    }
+}
+
+/// `$XDG_DATA_HOME`, or its default `~/.local/share`
+pub fn data_home() -> Option<PathBuf> {
+    env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::home_dir().map(|home| home.join(".local/share")))
+}
+
+/// Terminal emulator used to run commands that need one
+pub fn terminal() -> String {
+    env::var("TERMINAL").unwrap_or_else(|_| "xterm".to_string())
+}
+
+#[cfg(target_os = "windows")]
+const SHELL: [&str; 2] = ["cmd", "/C"];
+#[cfg(not(target_os = "windows"))]
+const SHELL: [&str; 2] = ["sh", "-c"];
+
+/// Run a command line in the shell without waiting for it
+pub fn spawn_shell(command: &str) -> anyhow::Result<()> {
+    Command::new(SHELL[0])
+        .args([SHELL[1], command])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to run `{command}`"))?;
+
+    Ok(())
+}
@@ -0,0 +1,360 @@
+use std::{fs, iter::Peekable, path::PathBuf, str::Chars};
+
+use anyhow::{Context, Result, anyhow, bail, ensure};
+
+use super::{Action, Mode, Sink, data_home};
+use crate::item::Item;
+
+/// Max number of calculations kept in the history
+const HISTORY_SIZE: usize = 100;
+
+/// Separates the expression and the result in the history
+const HISTORY_SEP: &str = " = ";
+
+/// Calculator, evaluates the input and keeps the previous calculations
+pub struct Calc {
+    history: Vec<String>,
+    history_path: Option<PathBuf>,
+}
+
+impl Calc {
+    pub fn new() -> Self {
+        let history_path = data_home().map(|dir| dir.join("emenu/calc_history"));
+        let history = history_path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|contents| contents.lines().map(String::from).collect())
+            .unwrap_or_default();
+
+        Self {
+            history,
+            history_path,
+        }
+    }
+
+    fn save_history(&mut self, entry: String) -> Result<()> {
+        self.history.retain(|e| e != &entry);
+        self.history.insert(0, entry);
+        self.history.truncate(HISTORY_SIZE);
+
+        let Some(path) = &self.history_path else {
+            return Ok(());
+        };
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.history.join("\n"))
+            .with_context(|| format!("Failed to save the history to `{}`", path.display()))
+    }
+}
+
+impl Mode for Calc {
+    fn name(&self) -> &str {
+        "calc"
+    }
+
+    fn load(&mut self, sink: Sink) {
+        for (i, entry) in self.history.iter().enumerate() {
+            sink.push(Item::new(i, entry.clone()));
+        }
+    }
+
+    fn accept(&mut self, selected: Option<&Item>, input: &str) -> Result<Action> {
+        // History entries print their result
+        if let Some(item) = selected {
+            let result = item.text.rsplit(HISTORY_SEP).next().unwrap_or(&item.text);
+            return Ok(Action::Print(result.to_string()));
+        }
+
+        let Ok(result) = eval(input) else {
+            return Ok(Action::Ignore);
+        };
+
+        let result = format_number(result);
+        self.save_history(format!("{}{HISTORY_SEP}{result}", input.trim()))?;
+
+        Ok(Action::Print(result))
+    }
+
+    fn live_result(&self, input: &str) -> Option<String> {
+        if input.trim().is_empty() {
+            return None;
+        }
+
+        Some(match eval(input) {
+            Ok(result) => format!("= {}", format_number(result)),
+            Err(e) => format!("{e}"),
+        })
+    }
+}
+
+/// Show integers without decimals and round away float noise like `0.1 + 0.2`
+fn format_number(n: f64) -> String {
+    if n.is_finite() && n.fract() == 0.0 && n.abs() < 1e15 {
+        return format!("{n:.0}");
+    }
+
+    if n != 0.0 && (n.abs() >= 1e15 || n.abs() < 1e-6) {
+        return format!("{n:e}");
+    }
+
+    let n = format!("{n:.12}");
+    n.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Evaluate an arithmetic expression
+fn eval(expr: &str) -> Result<f64> {
+    let mut parser = Parser {
+        chars: expr.chars().peekable(),
+    };
+
+    let result = parser.expr()?;
+    parser.skip_whitespace();
+
+    match parser.chars.next() {
+        Some(c) => bail!("Unexpected `{c}`"),
+        None => Ok(result),
+    }
+}
+
+/// Recursive descent parser that evaluates as it goes
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        self.skip_whitespace();
+        self.chars.next_if_eq(&expected).is_some()
+    }
+
+    /// expr = term (('+' | '-') term)*
+    fn expr(&mut self) -> Result<f64> {
+        let mut value = self.term()?;
+
+        loop {
+            if self.eat('+') {
+                value += self.term()?;
+            } else if self.eat('-') {
+                value -= self.term()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// term = unary (('*' | '/' | '%') unary)*
+    fn term(&mut self) -> Result<f64> {
+        let mut value = self.unary()?;
+
+        loop {
+            self.skip_whitespace();
+            let mut lookahead = self.chars.clone();
+            let is_power = lookahead.next() == Some('*') && lookahead.next() == Some('*');
+
+            if !is_power && self.eat('*') {
+                value *= self.unary()?;
+            } else if self.eat('/') {
+                value /= self.unary()?;
+            } else if self.eat('%') {
+                value %= self.unary()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// unary = ('-' | '+') unary | power
+    fn unary(&mut self) -> Result<f64> {
+        if self.eat('-') {
+            return Ok(-self.unary()?);
+        }
+        if self.eat('+') {
+            return self.unary();
+        }
+
+        self.power()
+    }
+
+    /// power = primary (('^' | '**') unary)?, right associative
+    fn power(&mut self) -> Result<f64> {
+        let base = self.primary()?;
+
+        self.skip_whitespace();
+        let mut lookahead = self.chars.clone();
+        let is_power = match lookahead.next() {
+            Some('^') => true,
+            Some('*') => lookahead.next() == Some('*'),
+            _ => false,
+        };
+
+        if !is_power {
+            return Ok(base);
+        }
+
+        if self.chars.next() == Some('*') {
+            self.chars.next();
+        }
+
+        Ok(base.powf(self.unary()?))
+    }
+
+    /// primary = number | '(' expr ')' | constant | function '(' expr (',' expr)* ')'
+    fn primary(&mut self) -> Result<f64> {
+        self.skip_whitespace();
+
+        match self.chars.peek() {
+            Some('(') => {
+                self.chars.next();
+                let value = self.expr()?;
+                ensure!(self.eat(')'), "Missing `)`");
+                Ok(value)
+            }
+            Some(c) if c.is_ascii_digit() || *c == '.' => self.number(),
+            Some(c) if c.is_alphabetic() => self.identifier(),
+            Some(c) => bail!("Unexpected `{c}`"),
+            None => bail!("Incomplete expression"),
+        }
+    }
+
+    fn number(&mut self) -> Result<f64> {
+        let mut literal = String::new();
+        while let Some(c) = self
+            .chars
+            .next_if(|c| c.is_ascii_alphanumeric() || *c == '.' || *c == '_')
+        {
+            literal.push(c);
+
+            // Exponent sign, like `1e-3`
+            if (c == 'e' || c == 'E')
+                && !literal.starts_with("0x")
+                && let Some(sign) = self.chars.next_if(|c| *c == '-' || *c == '+')
+            {
+                literal.push(sign);
+            }
+        }
+
+        let literal = literal.replace('_', "");
+        let radix = match literal.get(..2) {
+            Some("0x" | "0X") => Some(16),
+            Some("0b" | "0B") => Some(2),
+            Some("0o" | "0O") => Some(8),
+            _ => None,
+        };
+
+        let value = match radix {
+            Some(radix) => i64::from_str_radix(&literal[2..], radix)
+                .map(|n| n as f64)
+                .ok(),
+            None => literal.parse().ok(),
+        };
+
+        value.ok_or_else(|| anyhow!("Invalid number `{literal}`"))
+    }
+
+    fn identifier(&mut self) -> Result<f64> {
+        let mut name = String::new();
+        while let Some(c) = self.chars.next_if(|c| c.is_alphanumeric()) {
+            name.push(c);
+        }
+
+        match name.as_str() {
+            "pi" => return Ok(std::f64::consts::PI),
+            "tau" => return Ok(std::f64::consts::TAU),
+            "e" => return Ok(std::f64::consts::E),
+            _ => {}
+        }
+
+        ensure!(self.eat('('), "Unknown constant `{name}`");
+        let mut args = vec![self.expr()?];
+        while self.eat(',') {
+            args.push(self.expr()?);
+        }
+        ensure!(self.eat(')'), "Missing `)`");
+
+        let value = match (name.as_str(), args.as_slice()) {
+            ("sqrt", [x]) => x.sqrt(),
+            ("cbrt", [x]) => x.cbrt(),
+            ("abs", [x]) => x.abs(),
+            ("exp", [x]) => x.exp(),
+            ("ln", [x]) => x.ln(),
+            ("log", [x]) => x.log10(),
+            ("log", [x, base]) => x.log(*base),
+            ("log2", [x]) => x.log2(),
+            ("sin", [x]) => x.sin(),
+            ("cos", [x]) => x.cos(),
+            ("tan", [x]) => x.tan(),
+            ("asin", [x]) => x.asin(),
+            ("acos", [x]) => x.acos(),
+            ("atan", [x]) => x.atan(),
+            ("floor", [x]) => x.floor(),
+            ("ceil", [x]) => x.ceil(),
+            ("round", [x]) => x.round(),
+            ("min", [x, y]) => x.min(*y),
+            ("max", [x, y]) => x.max(*y),
+            _ => bail!("Unknown function `{name}` with {} arguments", args.len()),
+        };
+
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("1 + 2", 3.0)]
+    #[case("2 + 3 * 4", 14.0)]
+    #[case("(2 + 3) * 4", 20.0)]
+    #[case("10 / 4", 2.5)]
+    #[case("7 % 4", 3.0)]
+    #[case("2 ^ 3 ^ 2", 512.0)]
+    #[case("2 ** 10", 1024.0)]
+    #[case("-2 ^ 2", -4.0)]
+    #[case("--3", 3.0)]
+    #[case("0xff + 0b101", 260.0)]
+    #[case("0o17", 15.0)]
+    #[case("1_000 * 2", 2000.0)]
+    #[case("1.5e3", 1500.0)]
+    #[case("2e-1", 0.2)]
+    #[case("sqrt(16) + abs(-2)", 6.0)]
+    #[case("max(1, min(5, 3))", 3.0)]
+    #[case("log(100) + log(8, 2)", 5.0)]
+    #[case("floor(pi)", 3.0)]
+    fn test_eval(#[case] expr: &str, #[case] expected: f64) {
+        let result = eval(expr).unwrap();
+        assert!(
+            (result - expected).abs() < 1e-9,
+            "`{expr}` = {result}, expected {expected}"
+        );
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("1 +")]
+    #[case("(1 + 2")]
+    #[case("1 2")]
+    #[case("foo")]
+    #[case("sqrt(1, 2)")]
+    #[case("0xZZ")]
+    fn test_eval_error(#[case] expr: &str) {
+        assert!(eval(expr).is_err(), "`{expr}` should fail");
+    }
+
+    #[rstest]
+    #[case(4.0, "4")]
+    #[case(-12.0, "-12")]
+    #[case(0.1 + 0.2, "0.3")]
+    #[case(2.5, "2.5")]
+    #[case(1e20, "1e20")]
+    fn test_format_number(#[case] n: f64, #[case] expected: &str) {
+        assert_eq!(format_number(n), expected);
+    }
+}
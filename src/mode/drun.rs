@@ -0,0 +1,213 @@
+use std::{
+    collections::HashSet,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+
+use super::{Action, Mode, Sink, data_home, spawn_shell, terminal};
+use crate::item::Item;
+
+/// Applications from the freedesktop `.desktop` files
+#[derive(Default)]
+pub struct Drun {
+    entries: Vec<DesktopEntry>,
+}
+
+#[derive(Debug, PartialEq)]
+struct DesktopEntry {
+    name: String,
+    exec: String,
+    icon: Option<String>,
+    terminal: bool,
+}
+
+impl Mode for Drun {
+    fn name(&self) -> &str {
+        "drun"
+    }
+
+    fn load(&mut self, sink: Sink) {
+        let mut seen = HashSet::new();
+        self.entries.clear();
+
+        for dir in application_dirs() {
+            for (id, path) in desktop_files(&dir, "") {
+                // Files earlier in the data dirs override the later ones
+                if !seen.insert(id) {
+                    continue;
+                }
+
+                if let Some(entry) = fs::read_to_string(path)
+                    .ok()
+                    .and_then(|contents| parse_desktop_entry(&contents))
+                {
+                    self.entries.push(entry);
+                }
+            }
+        }
+
+        self.entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        for (i, entry) in self.entries.iter().enumerate() {
+            sink.push(Item {
+                icon: entry.icon.clone(),
+                ..Item::new(i, entry.name.clone())
+            });
+        }
+    }
+
+    fn accept(&mut self, selected: Option<&Item>, input: &str) -> Result<Action> {
+        let Some(entry) = selected.and_then(|item| self.entries.get(item.idx)) else {
+            spawn_shell(input)?;
+            return Ok(Action::Exit);
+        };
+
+        let command = expand_exec(&entry.exec, &entry.name);
+        match entry.terminal {
+            true => spawn_shell(&format!("{} -e {command}", terminal()))?,
+            false => spawn_shell(&command)?,
+        }
+
+        Ok(Action::Exit)
+    }
+}
+
+/// `applications` folders in the XDG data dirs, by priority
+fn application_dirs() -> Vec<PathBuf> {
+    let data_dirs =
+        env::var("XDG_DATA_DIRS").unwrap_or_else(|_| "/usr/local/share:/usr/share".to_string());
+
+    data_home()
+        .into_iter()
+        .chain(env::split_paths(&data_dirs))
+        .map(|dir| dir.join("applications"))
+        .collect()
+}
+
+/// `.desktop` files in a folder and its subfolders, with their desktop file id
+fn desktop_files(dir: &Path, prefix: &str) -> Vec<(String, PathBuf)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut files = Vec::new();
+    for entry in entries.map_while(Result::ok) {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if path.is_dir() {
+            files.extend(desktop_files(&path, &format!("{prefix}{name}-")));
+        } else if name.ends_with(".desktop") {
+            files.push((format!("{prefix}{name}"), path));
+        }
+    }
+
+    files
+}
+
+/// Parse the `[Desktop Entry]` group, returns `None` for entries that shouldn't be shown
+fn parse_desktop_entry(contents: &str) -> Option<DesktopEntry> {
+    let mut name = None;
+    let mut exec = None;
+    let mut icon = None;
+    let mut terminal = false;
+    let mut in_group = false;
+
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_group = line == "[Desktop Entry]";
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=').filter(|_| in_group) else {
+            continue;
+        };
+
+        match key.trim() {
+            "Type" if value.trim() != "Application" => return None,
+            "NoDisplay" | "Hidden" if value.trim() == "true" => return None,
+            "Name" => name = Some(value.trim().to_string()),
+            "Exec" => exec = Some(value.trim().to_string()),
+            "Icon" => icon = Some(value.trim().to_string()),
+            "Terminal" => terminal = value.trim() == "true",
+            _ => {}
+        }
+    }
+
+    Some(DesktopEntry {
+        name: name?,
+        exec: exec?,
+        icon,
+        terminal,
+    })
+}
+
+/// Remove the field codes of an `Exec` key, as no files or urls are passed
+fn expand_exec(exec: &str, name: &str) -> String {
+    let mut command = String::new();
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            command.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('%') => command.push('%'),
+            Some('c') => command.push_str(name),
+            _ => {}
+        }
+    }
+
+    command.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("firefox %u", "firefox")]
+    #[case("code --new-window %F", "code --new-window")]
+    #[case("app --title=%c", "app --title=Name")]
+    #[case("printf 100%%", "printf 100%")]
+    #[case("env A=1 app %f --flag", "env A=1 app --flag")]
+    fn test_expand_exec(#[case] exec: &str, #[case] expected: &str) {
+        assert_eq!(expand_exec(exec, "Name"), expected);
+    }
+
+    #[test]
+    fn test_parse_desktop_entry() {
+        let contents = "[Desktop Entry]\n\
+                        Type=Application\n\
+                        Name=Htop\n\
+                        Name[es]=Htop ES\n\
+                        Exec=htop\n\
+                        Icon=htop\n\
+                        Terminal=true\n\
+                        [Desktop Action New]\n\
+                        Name=Other\n";
+
+        assert_eq!(
+            parse_desktop_entry(contents),
+            Some(DesktopEntry {
+                name: "Htop".to_string(),
+                exec: "htop".to_string(),
+                icon: Some("htop".to_string()),
+                terminal: true,
+            })
+        );
+    }
+
+    #[rstest]
+    #[case("[Desktop Entry]\nType=Link\nName=a\nExec=a")]
+    #[case("[Desktop Entry]\nNoDisplay=true\nName=a\nExec=a")]
+    #[case("[Desktop Entry]\nName=a")]
+    fn test_parse_desktop_entry_hidden(#[case] contents: &str) {
+        assert_eq!(parse_desktop_entry(contents), None);
+    }
+}
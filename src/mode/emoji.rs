@@ -0,0 +1,117 @@
+use std::{ops::RangeInclusive, thread};
+
+use anyhow::Result;
+
+use super::{Action, Mode, Sink};
+use crate::item::Item;
+
+/// Unicode blocks with symbols worth picking besides the emoji
+const UNICODE_BLOCKS: [RangeInclusive<u32>; 11] = [
+    0x00A1..=0x00FF, // Latin-1 Supplement
+    0x0391..=0x03C9, // Greek
+    0x2010..=0x205E, // General Punctuation
+    0x20A0..=0x20C0, // Currency Symbols
+    0x2100..=0x214F, // Letterlike Symbols
+    0x2150..=0x218B, // Number Forms
+    0x2190..=0x21FF, // Arrows
+    0x2200..=0x22FF, // Mathematical Operators
+    0x2300..=0x23FF, // Miscellaneous Technical
+    0x2500..=0x25FF, // Box Drawing, Block Elements and Geometric Shapes
+    0x2600..=0x27BF, // Miscellaneous Symbols and Dingbats
+];
+
+/// Emoji and Unicode characters, matched by their name and keywords
+pub struct Emoji {
+    /// Print the codepoints instead of the character
+    pub codepoint: bool,
+}
+
+impl Mode for Emoji {
+    fn name(&self) -> &str {
+        "emoji"
+    }
+
+    fn load(&mut self, sink: Sink) {
+        thread::spawn(move || {
+            let emojis = emojis::iter().map(|emoji| {
+                let keywords = emoji.shortcodes().collect::<Vec<_>>().join(" ");
+                (
+                    emoji.as_str().to_string(),
+                    emoji.name().to_string(),
+                    keywords,
+                )
+            });
+
+            let chars = UNICODE_BLOCKS
+                .into_iter()
+                .flatten()
+                .filter_map(char::from_u32)
+                // Skip the ones already in the emoji table
+                .filter(|c| emojis::get(&c.to_string()).is_none())
+                .filter_map(|c| {
+                    let name = unicode_names2::name(c)?.to_string().to_lowercase();
+                    Some((c.to_string(), name, codepoints(&c.to_string())))
+                });
+
+            for (i, (glyph, name, keywords)) in emojis.chain(chars).enumerate() {
+                sink.push(Item {
+                    meta: Some(keywords),
+                    info: Some(glyph.clone()),
+                    ..Item::new(i, format!("{glyph}  {name}"))
+                });
+            }
+        });
+    }
+
+    fn accept(&mut self, selected: Option<&Item>, input: &str) -> Result<Action> {
+        let glyph = selected
+            .and_then(|item| item.info.as_deref())
+            .unwrap_or(input);
+
+        Ok(Action::Print(match self.codepoint {
+            true => codepoints(glyph),
+            false => glyph.to_string(),
+        }))
+    }
+}
+
+/// Codepoints in the `U+1F600` format
+fn codepoints(glyph: &str) -> String {
+    glyph
+        .chars()
+        .map(|c| format!("U+{:04X}", c as u32))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("→", "U+2192")]
+    #[case("😀", "U+1F600")]
+    #[case("👍🏻", "U+1F44D U+1F3FB")]
+    fn test_codepoints(#[case] glyph: &str, #[case] expected: &str) {
+        assert_eq!(codepoints(glyph), expected);
+    }
+
+    #[rstest]
+    #[case(false, "😀\n→")]
+    #[case(true, "U+1F600\nU+2192")]
+    fn test_accept_many(#[case] codepoint: bool, #[case] expected: &str) {
+        let items = [("😀", "grinning face"), ("→", "rightwards arrow")]
+            .into_iter()
+            .enumerate()
+            .map(|(i, (glyph, name))| Item {
+                info: Some(glyph.to_string()),
+                ..Item::new(i, format!("{glyph}  {name}"))
+            })
+            .collect::<Vec<_>>();
+
+        let action = Emoji { codepoint }.accept_many(&items);
+
+        assert!(matches!(action, Ok(Action::Print(text)) if text == expected));
+    }
+}
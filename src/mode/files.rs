@@ -0,0 +1,239 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+};
+
+use anyhow::Result;
+use eframe::egui::{Key, Modifiers};
+use ignore::WalkBuilder;
+
+use super::{Action, Mode, Sink};
+use crate::item::Item;
+
+/// File browser, enter opens directories and prints files
+pub struct Files {
+    dir: PathBuf,
+    prompt: String,
+    show_hidden: bool,
+    /// How deep to list the files, 1 is only the current directory
+    max_depth: usize,
+    /// Stops the listing of the previous directory
+    cancel: Arc<AtomicBool>,
+}
+
+impl Files {
+    pub fn new(dir: PathBuf, show_hidden: bool, max_depth: usize) -> Self {
+        Self {
+            dir: dir.canonicalize().unwrap_or(dir),
+            prompt: String::new(),
+            show_hidden,
+            max_depth,
+            cancel: Default::default(),
+        }
+    }
+}
+
+impl Mode for Files {
+    fn name(&self) -> &str {
+        "files"
+    }
+
+    fn load(&mut self, sink: Sink) {
+        self.prompt = format!("{}/", self.dir.display());
+
+        self.cancel.store(true, Ordering::Relaxed);
+        self.cancel = Default::default();
+        let cancel = self.cancel.clone();
+
+        let entries = list(&self.dir, self.show_hidden, self.max_depth);
+
+        thread::spawn(move || {
+            for (i, text) in entries.enumerate() {
+                if cancel.load(Ordering::Relaxed) {
+                    return;
+                }
+
+                sink.push(Item::new(i, text));
+            }
+        });
+    }
+
+    fn accept(&mut self, selected: Option<&Item>, input: &str) -> Result<Action> {
+        let path = self.dir.join(selected.map_or(input, |item| &item.text));
+
+        if path.is_dir() {
+            self.dir = path.canonicalize()?;
+            return Ok(Action::Reload);
+        }
+
+        Ok(Action::Print(path.to_string_lossy().to_string()))
+    }
+
+    /// The marked paths are printed, directories too instead of being opened
+    fn accept_many(&mut self, items: &[Item]) -> Result<Action> {
+        let paths = items
+            .iter()
+            .map(|item| self.dir.join(&item.text).to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+
+        Ok(Action::Print(paths.join("\n")))
+    }
+
+    fn key_pressed(&mut self, key: Key, modifiers: Modifiers, input: &str) -> Action {
+        // Backspace on an empty query goes to the parent
+        if key == Key::Backspace && modifiers.is_none() && input.is_empty() {
+            return match self.dir.parent() {
+                Some(parent) => {
+                    self.dir = parent.to_path_buf();
+                    Action::Reload
+                }
+                None => Action::Ignore,
+            };
+        }
+
+        // ctrl + h to toggle the hidden files
+        if key == Key::H && modifiers.matches_exact(Modifiers::CTRL) {
+            self.show_hidden = !self.show_hidden;
+            return Action::Reload;
+        }
+
+        Action::Ignore
+    }
+
+    fn prompt(&self) -> Option<&str> {
+        Some(&self.prompt)
+    }
+
+    fn path(&self, item: &Item) -> Option<PathBuf> {
+        Some(self.dir.join(&item.text))
+    }
+}
+
+/// Paths under `dir` sorted by name, relative to it and with a `/` after directories
+fn list(dir: &Path, show_hidden: bool, max_depth: usize) -> impl Iterator<Item = String> + use<> {
+    let dir = dir.to_path_buf();
+
+    WalkBuilder::new(&dir)
+        .hidden(!show_hidden)
+        .max_depth(Some(max_depth))
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build()
+        .map_while(Result::ok)
+        // The first entry is the directory itself
+        .skip(1)
+        .filter_map(move |entry| {
+            let mut text = entry
+                .path()
+                .strip_prefix(&dir)
+                .ok()?
+                .to_string_lossy()
+                .to_string();
+            if entry.file_type().is_some_and(|t| t.is_dir()) {
+                text.push('/');
+            }
+            Some(text)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::{env, fs};
+
+    /// Directory with `a.txt`, `.hidden` and `sub/b.txt`, removed when dropped
+    struct TempTree(PathBuf);
+
+    impl TempTree {
+        fn new(name: &str) -> Self {
+            let dir = env::temp_dir().join(format!("emenu-files-{}-{name}", std::process::id()));
+            fs::create_dir_all(dir.join("sub")).unwrap();
+            fs::write(dir.join("a.txt"), "").unwrap();
+            fs::write(dir.join(".hidden"), "").unwrap();
+            fs::write(dir.join("sub/b.txt"), "").unwrap();
+            Self(dir.canonicalize().unwrap())
+        }
+    }
+
+    impl Drop for TempTree {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[rstest]
+    #[case::current_dir(false, 1, &["a.txt", "sub/"])]
+    #[case::hidden(true, 1, &[".hidden", "a.txt", "sub/"])]
+    #[case::nested(false, 2, &["a.txt", "sub/", "sub/b.txt"])]
+    fn test_list(#[case] show_hidden: bool, #[case] max_depth: usize, #[case] expected: &[&str]) {
+        let tree = TempTree::new(&format!("list-{show_hidden}-{max_depth}"));
+
+        assert_eq!(
+            list(&tree.0, show_hidden, max_depth).collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_accept() {
+        let tree = TempTree::new("accept");
+        let mut files = Files::new(tree.0.clone(), false, 1);
+
+        let action = files.accept(Some(&Item::new(0, "a.txt".to_string())), "");
+        assert!(
+            matches!(action, Ok(Action::Print(path)) if path == tree.0.join("a.txt").to_string_lossy())
+        );
+
+        let action = files.accept(Some(&Item::new(1, "sub/".to_string())), "");
+        assert!(matches!(action, Ok(Action::Reload)));
+        assert_eq!(files.dir, tree.0.join("sub"));
+    }
+
+    #[test]
+    fn test_accept_many() {
+        let tree = TempTree::new("accept-many");
+        let mut files = Files::new(tree.0.clone(), false, 1);
+        let items = ["sub/", "a.txt"].map(|text| Item::new(0, text.to_string()));
+
+        let action = files.accept_many(&items);
+
+        let expected = format!(
+            "{}\n{}",
+            tree.0.join("sub/").display(),
+            tree.0.join("a.txt").display()
+        );
+        assert!(matches!(action, Ok(Action::Print(text)) if text == expected));
+        assert_eq!(files.dir, tree.0);
+    }
+
+    #[rstest]
+    #[case::parent(Key::Backspace, Modifiers::NONE, "", true, false)]
+    #[case::backspace_in_query(Key::Backspace, Modifiers::NONE, "a", false, false)]
+    #[case::toggle_hidden(Key::H, Modifiers::CTRL, "", false, true)]
+    #[case::other_key(Key::H, Modifiers::NONE, "", false, false)]
+    fn test_key_pressed(
+        #[case] key: Key,
+        #[case] modifiers: Modifiers,
+        #[case] input: &str,
+        #[case] to_parent: bool,
+        #[case] toggles_hidden: bool,
+    ) {
+        let tree = TempTree::new(&format!("key-{key:?}-{}-{input}", modifiers.ctrl));
+        let mut files = Files::new(tree.0.join("sub"), false, 1);
+
+        let action = files.key_pressed(key, modifiers, input);
+
+        let reloads = to_parent || toggles_hidden;
+        assert_eq!(matches!(action, Action::Reload), reloads);
+        let dir = match to_parent {
+            true => tree.0.clone(),
+            false => tree.0.join("sub"),
+        };
+        assert_eq!(files.dir, dir);
+        assert_eq!(files.show_hidden, toggles_hidden);
+    }
+}
@@ -0,0 +1,56 @@
+use std::{collections::BTreeSet, env, fs, path::Path, thread};
+
+use anyhow::Result;
+
+use super::{Action, Mode, Sink, spawn_shell};
+use crate::item::Item;
+
+/// Executables in `$PATH`
+pub struct Run;
+
+impl Mode for Run {
+    fn name(&self) -> &str {
+        "run"
+    }
+
+    fn load(&mut self, sink: Sink) {
+        thread::spawn(move || {
+            let Some(path) = env::var_os("PATH") else {
+                return;
+            };
+
+            let executables = env::split_paths(&path)
+                .filter_map(|dir| fs::read_dir(dir).ok())
+                .flat_map(|entries| entries.map_while(Result::ok))
+                .filter(|entry| is_executable(&entry.path()))
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .collect::<BTreeSet<_>>();
+
+            for (i, executable) in executables.into_iter().enumerate() {
+                sink.push(Item::new(i, executable));
+            }
+        });
+    }
+
+    fn accept(&mut self, selected: Option<&Item>, input: &str) -> Result<Action> {
+        spawn_shell(selected.map_or(input, |item| &item.text))?;
+        Ok(Action::Exit)
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(target_os = "windows")]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+        && path.extension().is_some_and(|ext| {
+            ["exe", "bat", "cmd", "com"]
+                .iter()
+                .any(|e| ext.eq_ignore_ascii_case(e))
+        })
+}
@@ -0,0 +1,228 @@
+use std::{path::PathBuf, process::Command};
+
+use anyhow::{Context, ensure};
+
+use super::{Action, Mode, Sink};
+use crate::item::Item;
+
+/// Separator between option names and values in the rofi script protocol
+const FIELD_SEP: char = '\x1f';
+
+/// Value of `ROFI_RETV` passed to the script
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Retv {
+    /// First call, without arguments
+    Initial = 0,
+    /// An entry was selected
+    Selected = 1,
+    /// A custom entry (the input) was accepted
+    Custom = 2,
+}
+
+/// A script that follows the rofi script protocol, see `rofi-script(5)`
+pub struct Script {
+    path: PathBuf,
+    /// Output of the last run
+    output: ScriptOutput,
+}
+
+/// Everything a single script invocation printed
+#[derive(Debug, Default, PartialEq)]
+struct ScriptOutput {
+    prompt: Option<String>,
+    message: Option<String>,
+    data: Option<String>,
+    /// Don't accept the input as a custom entry
+    no_custom: bool,
+    items: Vec<Item>,
+}
+
+impl Script {
+    /// Run the script for the first time
+    pub fn new(path: PathBuf) -> anyhow::Result<Self> {
+        let mut script = Self {
+            path,
+            output: ScriptOutput::default(),
+        };
+        script.run(Retv::Initial, None, None)?;
+
+        Ok(script)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.output.items.is_empty()
+    }
+
+    /// Run the script, `arg` is the text of the accepted entry and `info` its `info` option
+    fn run(&mut self, retv: Retv, arg: Option<&str>, info: Option<&str>) -> anyhow::Result<()> {
+        let mut cmd = Command::new(&self.path);
+        cmd.env("ROFI_RETV", (retv as u8).to_string());
+        cmd.args(arg);
+
+        if let Some(info) = info {
+            cmd.env("ROFI_INFO", info);
+        }
+        if let Some(data) = &self.output.data {
+            cmd.env("ROFI_DATA", data);
+        }
+
+        let output = cmd
+            .output()
+            .with_context(|| format!("Failed to run `{}`", self.path.display()))?;
+
+        ensure!(
+            output.status.success(),
+            "`{}` exited with {}",
+            self.path.display(),
+            output.status
+        );
+
+        self.output = parse_output(&String::from_utf8_lossy(&output.stdout));
+
+        Ok(())
+    }
+}
+
+impl Mode for Script {
+    fn name(&self) -> &str {
+        "script"
+    }
+
+    fn load(&mut self, sink: Sink) {
+        for item in self.output.items.drain(..) {
+            sink.push(item);
+        }
+    }
+
+    fn accept(&mut self, selected: Option<&Item>, input: &str) -> anyhow::Result<Action> {
+        match selected {
+            Some(item) => self.run(Retv::Selected, Some(&item.text), item.info.as_deref())?,
+            None if self.output.no_custom => return Ok(Action::Ignore),
+            None => self.run(Retv::Custom, Some(input), None)?,
+        }
+
+        // The script is done when it doesn't return any more entries
+        Ok(match self.is_empty() {
+            true => Action::Exit,
+            false => Action::Reload,
+        })
+    }
+
+    fn prompt(&self) -> Option<&str> {
+        self.output.prompt.as_deref()
+    }
+
+    fn message(&self) -> Option<&str> {
+        self.output.message.as_deref()
+    }
+}
+
+/// Parse the rows and mode options printed by a script
+fn parse_output(stdout: &str) -> ScriptOutput {
+    let mut output = ScriptOutput::default();
+    let mut urgent_rows = Vec::new();
+
+    for line in stdout.lines() {
+        // Mode options: `\0option\x1fvalue`
+        if let Some(option) = line.strip_prefix('\0') {
+            let (key, value) = option.split_once(FIELD_SEP).unwrap_or((option, ""));
+            match key {
+                "prompt" => output.prompt = Some(value.to_string()),
+                "message" => output.message = Some(value.to_string()),
+                "data" => output.data = Some(value.to_string()),
+                "no-custom" => output.no_custom = value == "true",
+                "urgent" => urgent_rows.extend(parse_rows(value)),
+                _ => {}
+            }
+            continue;
+        }
+
+        output.items.push(parse_row(output.items.len(), line));
+    }
+
+    for row in urgent_rows {
+        if let Some(item) = output.items.get_mut(row) {
+            item.urgent = true;
+        }
+    }
+
+    output
+}
+
+/// Parse a row with its options: `text\0option\x1fvalue\x1foption\x1fvalue`
+pub fn parse_row(idx: usize, line: &str) -> Item {
+    let (text, options) = line.split_once('\0').unwrap_or((line, ""));
+    let mut item = Item::new(idx, text.to_string());
+
+    let mut fields = options.split(FIELD_SEP);
+    while let (Some(key), Some(value)) = (fields.next(), fields.next()) {
+        match key {
+            "icon" => item.icon = Some(value.to_string()),
+            "meta" => item.meta = Some(value.to_string()),
+            "info" => item.info = Some(value.to_string()),
+            "nonselectable" => item.nonselectable = value == "true",
+            "urgent" => item.urgent = value == "true",
+            _ => {}
+        }
+    }
+
+    item
+}
+
+/// Parse a row list like `0,2,4-6`
+fn parse_rows(rows: &str) -> Vec<usize> {
+    rows.split(',')
+        .filter_map(|r| match r.trim().split_once('-') {
+            Some((start, end)) => Some((start.parse().ok()?..=end.parse().ok()?).collect()),
+            None => Some(vec![r.trim().parse().ok()?]),
+        })
+        .flatten()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("", vec![])]
+    #[case("3", vec![3])]
+    #[case("0,2", vec![0, 2])]
+    #[case("1,4-6", vec![1, 4, 5, 6])]
+    #[case("a,2", vec![2])]
+    fn test_parse_rows(#[case] rows: &str, #[case] expected: Vec<usize>) {
+        assert_eq!(parse_rows(rows), expected);
+    }
+
+    #[test]
+    fn test_parse_output() {
+        let stdout = "\0prompt\x1fPick\n\
+                      \0message\x1fSome <b>text</b>\n\
+                      \0urgent\x1f2\n\
+                      one\n\
+                      two\0icon\x1ffolder\x1finfo\x1f/tmp/two\n\
+                      three\0nonselectable\x1ftrue\n";
+        let output = parse_output(stdout);
+
+        assert_eq!(output.prompt.as_deref(), Some("Pick"));
+        assert_eq!(output.message.as_deref(), Some("Some <b>text</b>"));
+        assert!(!output.no_custom);
+        assert_eq!(
+            output.items,
+            vec![
+                Item::new(0, "one".to_string()),
+                Item {
+                    icon: Some("folder".to_string()),
+                    info: Some("/tmp/two".to_string()),
+                    ..Item::new(1, "two".to_string())
+                },
+                Item {
+                    nonselectable: true,
+                    urgent: true,
+                    ..Item::new(2, "three".to_string())
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use std::{
+    collections::HashSet,
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, Result};
+
+use super::{Action, Mode, Sink, terminal};
+use crate::item::Item;
+
+/// Max depth of nested `Include`s, same as ssh
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Hosts from `~/.ssh/config` and `~/.ssh/known_hosts`
+pub struct Ssh {
+    /// Open the host in `$TERMINAL` instead of printing it
+    pub connect: bool,
+}
+
+impl Mode for Ssh {
+    fn name(&self) -> &str {
+        "ssh"
+    }
+
+    fn load(&mut self, sink: Sink) {
+        let Some(ssh_dir) = env::home_dir().map(|home| home.join(".ssh")) else {
+            return;
+        };
+
+        let mut hosts = config_hosts(&ssh_dir.join("config"), &ssh_dir, 0);
+        if let Ok(contents) = fs::read_to_string(ssh_dir.join("known_hosts")) {
+            hosts.extend(known_hosts(&contents));
+        }
+
+        let mut seen = HashSet::new();
+        for host in hosts {
+            if seen.insert(host.clone()) {
+                sink.push(Item::new(seen.len() - 1, host));
+            }
+        }
+    }
+
+    fn accept(&mut self, selected: Option<&Item>, input: &str) -> Result<Action> {
+        let host = selected.map_or(input, |item| &item.text);
+
+        if !self.connect {
+            return Ok(Action::Print(host.to_string()));
+        }
+
+        // Run without a shell, and after `--` so a typed host isn't read as an ssh option
+        Command::new(terminal())
+            .args(["-e", "ssh", "--", host])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Failed to run ssh to `{host}`"))?;
+        Ok(Action::Exit)
+    }
+}
+
+/// Hosts without wildcards of an ssh config file and the files it includes
+fn config_hosts(path: &Path, ssh_dir: &Path, depth: usize) -> Vec<String> {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+
+    let mut hosts = Vec::new();
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        // Both `Keyword value` and `Keyword=value` are valid
+        let (keyword, args) = line
+            .split_once(|c: char| c.is_whitespace() || c == '=')
+            .unwrap_or((line, ""));
+        let args = args.trim_start_matches(|c: char| c.is_whitespace() || c == '=');
+
+        if keyword.eq_ignore_ascii_case("host") {
+            hosts.extend(
+                args.split_whitespace()
+                    .filter(|host| !host.contains(['*', '?', '!']))
+                    .map(String::from),
+            );
+        } else if keyword.eq_ignore_ascii_case("include") && depth < MAX_INCLUDE_DEPTH {
+            for pattern in args.split_whitespace() {
+                for include in include_paths(pattern, ssh_dir) {
+                    hosts.extend(config_hosts(&include, ssh_dir, depth + 1));
+                }
+            }
+        }
+    }
+
+    hosts
+}
+
+/// Files matched by an `Include` pattern, relative ones are in `~/.ssh`
+fn include_paths(pattern: &str, ssh_dir: &Path) -> Vec<PathBuf> {
+    let pattern = match pattern.strip_prefix("~/") {
+        Some(rest) => env::home_dir().unwrap_or_default().join(rest),
+        None => ssh_dir.join(pattern),
+    };
+
+    let Some(pattern) = pattern.to_str() else {
+        return Vec::new();
+    };
+
+    let Ok(paths) = glob::glob(pattern) else {
+        return Vec::new();
+    };
+
+    let mut paths = paths.map_while(Result::ok).collect::<Vec<_>>();
+    paths.sort();
+    paths
+}
+
+/// Hosts of a `known_hosts` file, skipping the hashed ones and those with a port
+fn known_hosts(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with(['#', '@', '|']))
+        .filter_map(|line| line.split_whitespace().next())
+        .flat_map(|hosts| hosts.split(','))
+        .filter(|host| !host.starts_with('[') && !host.contains(['*', '?', '!']))
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_hosts() {
+        let contents = "github.com ssh-ed25519 AAAA\n\
+                        # comment\n\
+                        server,10.0.0.2 ecdsa-sha2-nistp256 AAAA\n\
+                        |1|F1E1KeoE/eEWhi10WpGv4OdiO6Y=|3988QV0VE8wmZL7suNrYQLITLCg= ssh-rsa AAAA\n\
+                        [git.local]:2222 ssh-ed25519 AAAA\n\
+                        @revoked *.example.com ssh-rsa AAAA\n";
+
+        assert_eq!(
+            known_hosts(contents),
+            vec!["github.com", "server", "10.0.0.2"]
+        );
+    }
+
+    #[test]
+    fn test_config_hosts() {
+        let dir = env::temp_dir().join(format!("emenu-ssh-{}", std::process::id()));
+        fs::create_dir_all(dir.join("config.d")).unwrap();
+
+        fs::write(
+            dir.join("config"),
+            "Include config.d/*\n\
+             Host web db\n  User admin\n\
+             host=*.internal !bastion jump\n\
+             Host *\n  ForwardAgent no\n",
+        )
+        .unwrap();
+        fs::write(dir.join("config.d/work"), "Host work-vm\n").unwrap();
+
+        let hosts = config_hosts(&dir.join("config"), &dir, 0);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(hosts, vec!["work-vm", "web", "db", "jump"]);
+    }
+}
@@ -0,0 +1,48 @@
+use std::{io::stdin, path::PathBuf, thread};
+
+use anyhow::Result;
+
+use super::{Action, Mode, Sink, script::parse_row};
+use crate::item::Item;
+
+/// Lines read from stdin, with rofi's row options like `text\0icon\x1fname`
+pub struct Stdin {
+    /// Print the input when nothing matches, like dmenu
+    pub custom_entries: bool,
+}
+
+impl Mode for Stdin {
+    fn name(&self) -> &str {
+        "stdin"
+    }
+
+    fn load(&mut self, sink: Sink) {
+        // Read from stdin in another thread
+        // TODO: nucleo has to add support for --tac
+        thread::spawn(move || {
+            if atty::isnt(atty::Stream::Stdin) {
+                stdin()
+                    .lines()
+                    .map_while(Result::ok)
+                    .enumerate()
+                    .for_each(|(i, s)| sink.push(parse_row(i, &s)))
+            }
+        });
+    }
+
+    fn accept(&mut self, selected: Option<&Item>, input: &str) -> Result<Action> {
+        Ok(Action::Print(
+            selected.map_or(input, |item| &item.text).to_string(),
+        ))
+    }
+
+    /// The input is only printed when stdin is empty, unless it's dmenu
+    fn custom_entries(&self) -> bool {
+        self.custom_entries
+    }
+
+    /// Lines can be paths, like the output of `find`
+    fn path(&self, item: &Item) -> Option<PathBuf> {
+        Some(PathBuf::from(&item.text))
+    }
+}
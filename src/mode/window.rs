@@ -0,0 +1,270 @@
+use anyhow::{Context, Result};
+use x11rb::{
+    connection::Connection,
+    protocol::xproto::{AtomEnum, ClientMessageEvent, ConnectionExt, EventMask, Window as XWindow},
+    rust_connection::RustConnection,
+};
+
+use super::{Action, Mode, Sink};
+use crate::item::Item;
+
+x11rb::atom_manager! {
+    pub Atoms: AtomsCookie {
+        _NET_CLIENT_LIST,
+        _NET_ACTIVE_WINDOW,
+        _NET_WM_NAME,
+        _NET_WM_DESKTOP,
+        UTF8_STRING,
+    }
+}
+
+/// `_NET_WM_DESKTOP` of the windows shown on all desktops
+const ALL_DESKTOPS: u32 = 0xFFFFFFFF;
+
+/// Top-level X11 windows, accepting one activates it
+pub struct Window {
+    conn: RustConnection,
+    root: XWindow,
+    atoms: Atoms,
+}
+
+#[derive(Debug, PartialEq)]
+struct WindowInfo {
+    id: XWindow,
+    title: String,
+    class: String,
+    desktop: Option<u32>,
+}
+
+impl Window {
+    pub fn new() -> Result<Self> {
+        let (conn, screen) = x11rb::connect(None).context("Failed to connect to the X server")?;
+        let root = conn.setup().roots[screen].root;
+        let atoms = Atoms::new(&conn)?.reply()?;
+
+        Ok(Self { conn, root, atoms })
+    }
+}
+
+impl Mode for Window {
+    fn name(&self) -> &str {
+        "window"
+    }
+
+    fn load(&mut self, sink: Sink) {
+        let windows = match list_windows(&self.conn, self.root, &self.atoms) {
+            Ok(windows) => windows,
+            Err(e) => {
+                eprintln!("Failed to list the windows: {e}");
+                return;
+            }
+        };
+
+        for (i, window) in windows.into_iter().enumerate() {
+            let desktop = match window.desktop {
+                Some(ALL_DESKTOPS) => "*".to_string(),
+                Some(desktop) => desktop.to_string(),
+                None => " ".to_string(),
+            };
+
+            sink.push(Item {
+                info: Some(window.id.to_string()),
+                ..Item::new(
+                    i,
+                    format!("{desktop:>2}  {:<16}  {}", window.class, window.title),
+                )
+            });
+        }
+    }
+
+    fn accept(&mut self, selected: Option<&Item>, _input: &str) -> Result<Action> {
+        let Some(id) = selected
+            .and_then(|item| item.info.as_deref())
+            .and_then(|id| id.parse().ok())
+        else {
+            return Ok(Action::Ignore);
+        };
+
+        activate_window(&self.conn, self.root, &self.atoms, id)?;
+        Ok(Action::Exit)
+    }
+
+    fn custom_entries(&self) -> bool {
+        false
+    }
+}
+
+/// Windows in `_NET_CLIENT_LIST` with their title, class and desktop
+fn list_windows(conn: &impl Connection, root: XWindow, atoms: &Atoms) -> Result<Vec<WindowInfo>> {
+    let clients = conn
+        .get_property(
+            false,
+            root,
+            atoms._NET_CLIENT_LIST,
+            AtomEnum::WINDOW,
+            0,
+            u32::MAX,
+        )?
+        .reply()?;
+
+    let Some(ids) = clients.value32() else {
+        return Ok(Vec::new());
+    };
+
+    // Windows can close while they're queried, skip them instead of failing the list
+    Ok(ids
+        .filter_map(|id| window_info(conn, id, atoms).ok())
+        .collect())
+}
+
+/// Title, class and desktop of a window
+fn window_info(conn: &impl Connection, id: XWindow, atoms: &Atoms) -> Result<WindowInfo> {
+    let title = get_text(conn, id, atoms._NET_WM_NAME, atoms.UTF8_STRING)?;
+    let title = match title.is_empty() {
+        true => get_text(conn, id, AtomEnum::WM_NAME.into(), AtomEnum::STRING.into())?,
+        false => title,
+    };
+
+    // WM_CLASS is the instance and the class, separated by a nul
+    let class = get_text(conn, id, AtomEnum::WM_CLASS.into(), AtomEnum::STRING.into())?;
+    let class = class.split('\0').nth(1).unwrap_or_default().to_string();
+
+    let desktop = conn
+        .get_property(false, id, atoms._NET_WM_DESKTOP, AtomEnum::CARDINAL, 0, 1)?
+        .reply()?
+        .value32()
+        .and_then(|mut desktop| desktop.next());
+
+    Ok(WindowInfo {
+        id,
+        title,
+        class,
+        desktop,
+    })
+}
+
+/// Text property of a window, empty if it's not set
+fn get_text(conn: &impl Connection, window: XWindow, property: u32, kind: u32) -> Result<String> {
+    let reply = conn
+        .get_property(false, window, property, kind, 0, u32::MAX)?
+        .reply()?;
+
+    Ok(String::from_utf8_lossy(&reply.value)
+        .trim_end_matches('\0')
+        .to_string())
+}
+
+/// Ask the window manager to activate the window, switching to its desktop
+fn activate_window(
+    conn: &impl Connection,
+    root: XWindow,
+    atoms: &Atoms,
+    window: XWindow,
+) -> Result<()> {
+    // Source indication 2 is a pager, which window managers always obey
+    let event = ClientMessageEvent::new(32, window, atoms._NET_ACTIVE_WINDOW, [2, 0, 0, 0, 0]);
+
+    conn.send_event(
+        false,
+        root,
+        EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+        event,
+    )?;
+    conn.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use x11rb::{
+        protocol::xproto::{CreateWindowAux, PropMode, WindowClass},
+        wrapper::ConnectionExt as _,
+    };
+
+    /// There is no window manager in Xvfb, so the test sets `_NET_CLIENT_LIST` itself
+    #[test]
+    #[ignore = "needs an X server, run with `xvfb-run cargo test -- --ignored`"]
+    fn test_list_windows() {
+        let window = Window::new().unwrap();
+        let conn = &window.conn;
+        let atoms = &window.atoms;
+
+        let create = |title: &str, class: &[u8], desktop: u32| {
+            let id = conn.generate_id().unwrap();
+            conn.create_window(
+                0,
+                id,
+                window.root,
+                0,
+                0,
+                100,
+                100,
+                0,
+                WindowClass::INPUT_OUTPUT,
+                0,
+                &CreateWindowAux::new(),
+            )
+            .unwrap();
+            conn.change_property8(
+                PropMode::REPLACE,
+                id,
+                atoms._NET_WM_NAME,
+                atoms.UTF8_STRING,
+                title.as_bytes(),
+            )
+            .unwrap();
+            conn.change_property8(
+                PropMode::REPLACE,
+                id,
+                AtomEnum::WM_CLASS,
+                AtomEnum::STRING,
+                class,
+            )
+            .unwrap();
+            conn.change_property32(
+                PropMode::REPLACE,
+                id,
+                atoms._NET_WM_DESKTOP,
+                AtomEnum::CARDINAL,
+                &[desktop],
+            )
+            .unwrap();
+            id
+        };
+
+        let term = create("~/emenu — fish", b"term\0Alacritty\0", 0);
+        let browser = create("Docs", b"browser\0Firefox\0", ALL_DESKTOPS);
+
+        conn.change_property32(
+            PropMode::REPLACE,
+            window.root,
+            atoms._NET_CLIENT_LIST,
+            AtomEnum::WINDOW,
+            &[term, browser],
+        )
+        .unwrap();
+        conn.flush().unwrap();
+
+        assert_eq!(
+            list_windows(conn, window.root, atoms).unwrap(),
+            vec![
+                WindowInfo {
+                    id: term,
+                    title: "~/emenu — fish".to_string(),
+                    class: "Alacritty".to_string(),
+                    desktop: Some(0),
+                },
+                WindowInfo {
+                    id: browser,
+                    title: "Docs".to_string(),
+                    class: "Firefox".to_string(),
+                    desktop: Some(ALL_DESKTOPS),
+                },
+            ]
+        );
+
+        activate_window(conn, window.root, atoms, term).unwrap();
+    }
+}
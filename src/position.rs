@@ -0,0 +1,316 @@
+use anyhow::{Context, bail};
+use eframe::egui::{Pos2, Rect, Vec2, pos2, vec2};
+
+/// Where the window is placed on the monitor
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Position {
+    Center,
+    Top,
+    Bottom,
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    /// Top-left corner at the mouse pointer
+    Pointer,
+    /// Top-left corner at these coordinates of the monitor
+    At(f32, f32),
+}
+
+/// Parse a position name like `top-left`, or `x,y` coordinates
+pub fn parse_position(position: &str) -> anyhow::Result<Position> {
+    Ok(match position.to_lowercase().as_str() {
+        "center" => Position::Center,
+        "top" => Position::Top,
+        "bottom" => Position::Bottom,
+        "left" => Position::Left,
+        "right" => Position::Right,
+        "top-left" => Position::TopLeft,
+        "top-right" => Position::TopRight,
+        "bottom-left" => Position::BottomLeft,
+        "bottom-right" => Position::BottomRight,
+        "pointer" => Position::Pointer,
+        coords => {
+            let (x, y) = coords
+                .split_once(',')
+                .context("Expected a position name or x,y")?;
+            Position::At(x.trim().parse()?, y.trim().parse()?)
+        }
+    })
+}
+
+/// A size in points or relative to the monitor
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Size {
+    Points(f32),
+    Percent(f32),
+}
+
+impl Size {
+    pub fn resolve(self, monitor: f32) -> f32 {
+        match self {
+            Size::Points(points) => points,
+            Size::Percent(percent) => monitor * percent / 100.0,
+        }
+    }
+}
+
+/// Parse a size like `480` or `40%`
+pub fn parse_size(size: &str) -> anyhow::Result<Size> {
+    let size = match size.strip_suffix('%') {
+        Some(percent) => Size::Percent(percent.trim().parse()?),
+        None => Size::Points(size.trim().parse()?),
+    };
+
+    match size {
+        Size::Points(n) | Size::Percent(n) if n <= 0.0 => bail!("The size must be positive"),
+        _ => Ok(size),
+    }
+}
+
+/// Monitor the window is shown on
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Monitor {
+    Index(usize),
+    /// The one with the focused window
+    Focused,
+    /// The one with the mouse pointer
+    Pointer,
+}
+
+/// Parse a monitor index, `focused` or `pointer`
+pub fn parse_monitor(monitor: &str) -> anyhow::Result<Monitor> {
+    Ok(match monitor.to_lowercase().as_str() {
+        "focused" => Monitor::Focused,
+        "pointer" => Monitor::Pointer,
+        index => Monitor::Index(index.parse()?),
+    })
+}
+
+/// Area of the monitor and where the pointer is
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Screen {
+    pub monitor: Rect,
+    pub pointer: Option<Pos2>,
+}
+
+impl Screen {
+    /// The screen in points, from one in physical pixels
+    pub fn to_points(self, pixels_per_point: f32) -> Self {
+        Self {
+            monitor: self.monitor / pixels_per_point,
+            pointer: self
+                .pointer
+                .map(|p| (p.to_vec2() / pixels_per_point).to_pos2()),
+        }
+    }
+}
+
+/// Where to put the window, the screen is unknown until found
+pub struct Placement {
+    pub position: Position,
+    pub offset: Vec2,
+    pub screen: Option<Screen>,
+}
+
+impl Placement {
+    /// Top-left corner of a window of `size`, if the screen is known
+    pub fn window_pos(&self, size: Vec2) -> Option<Pos2> {
+        self.screen
+            .map(|screen| window_pos(screen, size, self.position, self.offset))
+    }
+}
+
+/// Top-left corner of a window of `size` on the screen
+pub fn window_pos(screen: Screen, size: Vec2, position: Position, offset: Vec2) -> Pos2 {
+    let monitor = screen.monitor;
+    let left = monitor.left();
+    let center_x = monitor.center().x - size.x / 2.0;
+    let right = monitor.right() - size.x;
+    let top = monitor.top();
+    let center_y = monitor.center().y - size.y / 2.0;
+    let bottom = monitor.bottom() - size.y;
+
+    let pos = match position {
+        Position::Center => pos2(center_x, center_y),
+        Position::Top => pos2(center_x, top),
+        Position::Bottom => pos2(center_x, bottom),
+        Position::Left => pos2(left, center_y),
+        Position::Right => pos2(right, center_y),
+        Position::TopLeft => pos2(left, top),
+        Position::TopRight => pos2(right, top),
+        Position::BottomLeft => pos2(left, bottom),
+        Position::BottomRight => pos2(right, bottom),
+        Position::Pointer => {
+            let pointer = screen.pointer.unwrap_or(monitor.center());
+            // Keep the window inside the monitor
+            pos2(
+                pointer.x.min(right).max(left),
+                pointer.y.min(bottom).max(top),
+            )
+        }
+        Position::At(x, y) => monitor.min + vec2(x, y),
+    };
+
+    pos + offset
+}
+
+/// Find the monitor with X11's RandR in physical pixels, `None` when there's no X server
+#[cfg(not(target_os = "windows"))]
+pub fn find_screen(monitor: Option<Monitor>) -> Option<Screen> {
+    use x11rb::{
+        connection::Connection,
+        protocol::{
+            randr::ConnectionExt as _,
+            xproto::{AtomEnum, ConnectionExt as _},
+        },
+    };
+
+    let (conn, screen) = x11rb::connect(None).ok()?;
+    let root = conn.setup().roots[screen].root;
+
+    let monitors = conn.randr_get_monitors(root, true).ok()?.reply().ok()?;
+    let rects = monitors
+        .monitors
+        .iter()
+        .map(|m| {
+            Rect::from_min_size(
+                pos2(m.x as f32, m.y as f32),
+                vec2(m.width as f32, m.height as f32),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let pointer = conn
+        .query_pointer(root)
+        .ok()
+        .and_then(|cookie| cookie.reply().ok())
+        .map(|reply| pos2(reply.root_x as f32, reply.root_y as f32));
+
+    // Center of the focused window
+    let focused = || {
+        let atom = conn
+            .intern_atom(true, b"_NET_ACTIVE_WINDOW")
+            .ok()?
+            .reply()
+            .ok()?
+            .atom;
+        let window = conn
+            .get_property(false, root, atom, AtomEnum::WINDOW, 0, 1)
+            .ok()?
+            .reply()
+            .ok()?
+            .value32()?
+            .next()?;
+        let geometry = conn.get_geometry(window).ok()?.reply().ok()?;
+        let origin = conn
+            .translate_coordinates(window, root, 0, 0)
+            .ok()?
+            .reply()
+            .ok()?;
+
+        Some(pos2(
+            origin.dst_x as f32 + geometry.width as f32 / 2.0,
+            origin.dst_y as f32 + geometry.height as f32 / 2.0,
+        ))
+    };
+
+    let containing = |point: Option<Pos2>| point.and_then(|p| rects.iter().find(|r| r.contains(p)));
+    let primary = monitors
+        .monitors
+        .iter()
+        .position(|m| m.primary)
+        .and_then(|i| rects.get(i));
+
+    let monitor = match monitor {
+        Some(Monitor::Index(i)) => rects.get(i),
+        Some(Monitor::Focused) => containing(focused()).or(containing(pointer)),
+        Some(Monitor::Pointer) => containing(pointer),
+        None => None,
+    };
+
+    Some(Screen {
+        monitor: *monitor.or(primary).or(rects.first())?,
+        pointer,
+    })
+}
+
+#[cfg(target_os = "windows")]
+pub fn find_screen(_monitor: Option<Monitor>) -> Option<Screen> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("top-left", Position::TopLeft)]
+    #[case("Bottom", Position::Bottom)]
+    #[case("pointer", Position::Pointer)]
+    #[case("10, 20.5", Position::At(10.0, 20.5))]
+    fn test_parse_position(#[case] position: &str, #[case] expected: Position) {
+        assert_eq!(parse_position(position).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case("480", Size::Points(480.0))]
+    #[case("40%", Size::Percent(40.0))]
+    fn test_parse_size(#[case] size: &str, #[case] expected: Size) {
+        assert_eq!(parse_size(size).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case("middle")]
+    #[case("1,")]
+    fn test_parse_position_error(#[case] position: &str) {
+        assert!(parse_position(position).is_err());
+    }
+
+    #[rstest]
+    #[case("-10")]
+    #[case("0%")]
+    #[case("wide")]
+    fn test_parse_size_error(#[case] size: &str) {
+        assert!(parse_size(size).is_err());
+    }
+
+    #[rstest]
+    #[case(Position::Center, Vec2::ZERO, pos2(2780.0, 450.0))]
+    #[case(Position::Top, Vec2::ZERO, pos2(2780.0, 0.0))]
+    #[case(Position::BottomRight, Vec2::ZERO, pos2(3640.0, 900.0))]
+    #[case(Position::Left, vec2(10.0, -5.0), pos2(1930.0, 445.0))]
+    #[case(Position::At(100.0, 50.0), Vec2::ZERO, pos2(2020.0, 50.0))]
+    #[case(Position::Pointer, Vec2::ZERO, pos2(2500.0, 900.0))]
+    fn test_window_pos(#[case] position: Position, #[case] offset: Vec2, #[case] expected: Pos2) {
+        // Second monitor of two 1920x1080 side by side
+        let screen = Screen {
+            monitor: Rect::from_min_size(pos2(1920.0, 0.0), vec2(1920.0, 1080.0)),
+            pointer: Some(pos2(2500.0, 1000.0)),
+        };
+
+        assert_eq!(
+            window_pos(screen, vec2(200.0, 180.0), position, offset),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_screen_to_points() {
+        let screen = Screen {
+            monitor: Rect::from_min_size(pos2(3840.0, 0.0), vec2(3840.0, 2160.0)),
+            pointer: Some(pos2(4000.0, 300.0)),
+        };
+
+        assert_eq!(
+            screen.to_points(2.0),
+            Screen {
+                monitor: Rect::from_min_size(pos2(1920.0, 0.0), vec2(1920.0, 1080.0)),
+                pointer: Some(pos2(2000.0, 150.0)),
+            }
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+use eframe::egui::{ColorImage, Context, TextureHandle, TextureOptions};
+
+use crate::icon;
+
+/// Max width and height of the thumbnails, in pixels
+const THUMBNAIL_SIZE: u32 = 512;
+
+/// Number of thumbnails kept in memory
+const CACHE_SIZE: usize = 32;
+
+/// Least recently used entries are dropped once it's full
+struct Lru<K, V> {
+    capacity: usize,
+    /// Most recently used first
+    entries: VecDeque<(K, V)>,
+}
+
+impl<K: PartialEq, V> Lru<K, V> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// The value of `key`, marking it as the most recently used
+    fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: PartialEq + ?Sized,
+    {
+        let pos = self.entries.iter().position(|(k, _)| k.borrow() == key)?;
+        let entry = self.entries.remove(pos)?;
+        self.entries.push_front(entry);
+        self.entries.front().map(|(_, v)| v)
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        self.entries.retain(|(k, _)| *k != key);
+        self.entries.push_front((key, value));
+        self.entries.truncate(self.capacity);
+    }
+}
+
+/// Thumbnails of image files, decoded in another thread
+pub struct Previews {
+    ctx: Context,
+    /// `None` when the file isn't an image
+    cache: Lru<PathBuf, Option<TextureHandle>>,
+    /// Last file sent to be decoded
+    requested: Option<PathBuf>,
+    requests: Sender<PathBuf>,
+    decoded: Receiver<(PathBuf, Option<ColorImage>)>,
+}
+
+impl Previews {
+    pub fn new(ctx: Context) -> Self {
+        let (requests, pending) = mpsc::channel::<PathBuf>();
+        let (done, decoded) = mpsc::channel();
+        let repaint = ctx.clone();
+
+        thread::spawn(move || {
+            while let Ok(path) = pending.recv() {
+                // Skip the files that were scrolled past while decoding
+                let path = pending.try_iter().last().unwrap_or(path);
+                let image = icon::decode(&path, THUMBNAIL_SIZE);
+                if done.send((path, image)).is_err() {
+                    break;
+                }
+                repaint.request_repaint();
+            }
+        });
+
+        Self {
+            ctx,
+            cache: Lru::new(CACHE_SIZE),
+            requested: None,
+            requests,
+            decoded,
+        }
+    }
+
+    /// Thumbnail of the file, `None` while it's decoding or if it isn't an image
+    pub fn get(&mut self, path: &Path) -> Option<&TextureHandle> {
+        while let Ok((path, image)) = self.decoded.try_recv() {
+            let texture = image.map(|image| {
+                self.ctx
+                    .load_texture(path.to_string_lossy(), image, TextureOptions::LINEAR)
+            });
+            self.cache.insert(path, texture);
+        }
+
+        if self.cache.get(path).is_none() && self.requested.as_deref() != Some(path) {
+            let _ = self.requests.send(path.to_path_buf());
+            self.requested = Some(path.to_path_buf());
+        }
+
+        self.cache.get(path)?.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_evicts_least_recently_used() {
+        let mut lru = Lru::new(2);
+        lru.insert("a", 1);
+        lru.insert("b", 2);
+
+        // Using `a` makes `b` the least recently used
+        assert_eq!(lru.get("a"), Some(&1));
+        lru.insert("c", 3);
+
+        assert_eq!(lru.get("b"), None);
+        assert_eq!(lru.get("a"), Some(&1));
+        assert_eq!(lru.get("c"), Some(&3));
+    }
+
+    #[test]
+    fn test_lru_insert_replaces() {
+        let mut lru = Lru::new(2);
+        lru.insert("a", 1);
+        lru.insert("b", 2);
+        lru.insert("a", 3);
+        lru.insert("c", 4);
+
+        assert_eq!(lru.get("a"), Some(&3));
+        assert_eq!(lru.get("b"), None);
+        assert_eq!(lru.entries.len(), 2);
+    }
+}